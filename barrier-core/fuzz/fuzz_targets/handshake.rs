@@ -0,0 +1,18 @@
+#![no_main]
+
+use barrier_core::barrier;
+use barrier_core_fuzz::{hid_actuator, FuzzStream};
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+
+// The whole client from the server's greeting on, every packet is turned into HID reports
+fuzz_target!(|data: &[u8]| {
+    let mut actor = hid_actuator();
+    block_on(barrier::start(
+        FuzzStream(data),
+        "fuzz",
+        &mut actor,
+        &mut || {},
+    ))
+    .ok();
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use barrier_core::barrier::{Capture, PacketStream};
+use barrier_core_fuzz::FuzzStream;
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+
+// Small enough that most inputs wrap around the capture ring
+const CAPTURE_SIZE: usize = 64;
+
+// Framing and packet parsing, packets are read until the input runs out or is rejected
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = [0; CAPTURE_SIZE];
+    let mut capture = Capture::new(&mut buffer);
+    let mut stream = PacketStream::with_capture(FuzzStream(data), &mut capture);
+    block_on(async { while stream.read().await.is_ok() {} });
+
+    let mut exported = vec![0; capture.len()];
+    block_on(capture.export(exported.as_mut_slice())).unwrap();
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use barrier_core::barrier;
+use barrier_core_fuzz::hid_actuator;
+use futures::executor::block_on;
+use libfuzzer_sys::fuzz_target;
+
+// Capture records, the input is played back like an exported capture
+fuzz_target!(|data: &[u8]| {
+    let mut actor = hid_actuator();
+    block_on(barrier::replay(data, "fuzz", &mut actor)).ok();
+});
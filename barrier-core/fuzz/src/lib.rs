@@ -0,0 +1,46 @@
+use std::convert::Infallible;
+
+use barrier_core::{
+    hid_actuator::{HidActuator, ReportSink},
+    synergy_hid::{ReportType, SynergyHid},
+};
+use embedded_io_async::{ErrorType, Read, Write};
+
+/**
+ * Transport that hands the fuzzer input to the client as if the server sent it, then reports the
+ * end of the stream. What the client writes is discarded.
+ */
+pub struct FuzzStream<'a>(pub &'a [u8]);
+
+impl ErrorType for FuzzStream<'_> {
+    type Error = Infallible;
+}
+
+impl Read for FuzzStream<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let n = std::cmp::min(buf.len(), self.0.len());
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+impl Write for FuzzStream<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        Ok(buf.len())
+    }
+}
+
+/**
+ * Drops the reports, the shared actuator still makes them so whatever the server sends reaches
+ * `SynergyHid`.
+ */
+pub struct DropSink;
+
+impl ReportSink for DropSink {
+    async fn send_report(&mut self, _report: (ReportType, &[u8])) {}
+}
+
+pub fn hid_actuator() -> HidActuator<DropSink> {
+    HidActuator::new(1920, 1080, SynergyHid::new(true), DropSink)
+}
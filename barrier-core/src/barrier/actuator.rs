@@ -1,5 +1,7 @@
 // use std::collections::HashMap;
 
+use super::Options;
+
 pub trait Actuator {
     #[must_use]
     async fn connected(&mut self);
@@ -41,11 +43,17 @@ pub trait Actuator {
     #[must_use]
     async fn key_up(&mut self, key: u16, mask: u16, button: u16);
 
+    #[must_use]
+    async fn set_clipboard(&mut self, id: u8, text: &str);
+
+    #[must_use]
+    async fn set_options(&mut self, options: Options);
+
     #[must_use]
     async fn reset_options(&mut self);
 
     #[must_use]
-    async fn enter(&mut self);
+    async fn enter(&mut self, x: u16, y: u16, mask: u16);
 
     #[must_use]
     async fn leave(&mut self);
@@ -0,0 +1,188 @@
+use embedded_io_async::{ErrorType, Read, Write};
+
+use super::PacketError;
+
+// Timestamp in milliseconds and length of the captured frame, both u32 big endian
+pub const RECORD_HEADER_SIZE: usize = 8;
+
+struct Pending {
+    start: usize,
+    len: usize,
+    remaining: usize,
+}
+
+/**
+ * Ring buffer of the raw frames received from the server, the oldest frames are dropped when it's
+ * full. Each record is a header followed by the frame as it was on the wire, starting with its own
+ * length prefix. Frames that don't fit in the buffer are truncated.
+ * The exported records are in the same format, oldest first, and can be played back with `Replay`.
+ */
+pub struct Capture<'a> {
+    buf: &'a mut [u8],
+    head: usize,
+    len: usize,
+    pending: Option<Pending>,
+    dropped: usize,
+}
+
+impl<'a> Capture<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            head: 0,
+            len: 0,
+            pending: None,
+            dropped: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.pending = None;
+        self.dropped = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Number of bytes an export writes
+    pub fn len(&self) -> usize {
+        match &self.pending {
+            Some(pending) if pending.remaining > 0 => self.distance(pending.start),
+            _ => self.len,
+        }
+    }
+
+    // Number of frames dropped to make room for newer ones
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /**
+     * Starts recording a frame of `size` bytes, the length prefix included.
+     */
+    pub fn begin(&mut self, timestamp_ms: u32, size: usize) {
+        self.end();
+        let capacity = self.buf.len();
+        if capacity <= RECORD_HEADER_SIZE {
+            return;
+        }
+        let len = core::cmp::min(size, capacity - RECORD_HEADER_SIZE);
+        while capacity - self.len < RECORD_HEADER_SIZE + len {
+            self.drop_oldest();
+        }
+        let start = (self.head + self.len) % capacity;
+        self.push(&timestamp_ms.to_be_bytes());
+        self.push(&(len as u32).to_be_bytes());
+        self.pending = Some(Pending {
+            start,
+            len,
+            remaining: len,
+        });
+    }
+
+    pub fn record(&mut self, data: &[u8]) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        let n = core::cmp::min(data.len(), pending.remaining);
+        pending.remaining -= n;
+        self.push(&data[..n]);
+    }
+
+    /**
+     * Finishes the current frame, if the connection broke in the middle of it only what was
+     * received is kept.
+     */
+    pub fn end(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        if pending.remaining > 0 {
+            let len = (pending.len - pending.remaining) as u32;
+            self.write_at(pending.start + 4, &len.to_be_bytes());
+        }
+    }
+
+    /**
+     * Writes the captured records, oldest first. A frame that is still being received is left out.
+     */
+    pub async fn export<W: Write>(&self, mut out: W) -> Result<(), PacketError> {
+        let len = self.len();
+        let start = self.head;
+        let end = core::cmp::min(start + len, self.buf.len());
+        out.write_all(&self.buf[start..end])
+            .await
+            .map_err(|_| PacketError::IoError)?;
+        out.write_all(&self.buf[..len - (end - start)])
+            .await
+            .map_err(|_| PacketError::IoError)?;
+        out.flush().await.map_err(|_| PacketError::IoError)
+    }
+
+    fn distance(&self, pos: usize) -> usize {
+        (pos + self.buf.len() - self.head) % self.buf.len()
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.write_at(self.head + self.len, data);
+        self.len += data.len();
+    }
+
+    fn write_at(&mut self, pos: usize, data: &[u8]) {
+        let capacity = self.buf.len();
+        for (i, b) in data.iter().enumerate() {
+            self.buf[(pos + i) % capacity] = *b;
+        }
+    }
+
+    fn drop_oldest(&mut self) {
+        let capacity = self.buf.len();
+        let mut len = [0; 4];
+        for (i, b) in len.iter_mut().enumerate() {
+            *b = self.buf[(self.head + 4 + i) % capacity];
+        }
+        let size = RECORD_HEADER_SIZE + u32::from_be_bytes(len) as usize;
+        self.head = (self.head + size) % capacity;
+        self.len -= size;
+        self.dropped += 1;
+    }
+}
+
+/**
+ * Reader that copies everything it reads into the capture, the frame ends when it's dropped.
+ */
+pub(crate) struct CaptureReader<'a, 'b, S> {
+    inner: &'a mut S,
+    capture: Option<&'a mut Capture<'b>>,
+}
+
+impl<'a, 'b, S: Read> CaptureReader<'a, 'b, S> {
+    pub fn new(inner: &'a mut S, capture: Option<&'a mut Capture<'b>>) -> Self {
+        Self { inner, capture }
+    }
+}
+
+impl<'a, 'b, S: Read> ErrorType for CaptureReader<'a, 'b, S> {
+    type Error = S::Error;
+}
+
+impl<'a, 'b, S: Read> Read for CaptureReader<'a, 'b, S> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, S::Error> {
+        let n = self.inner.read(buf).await?;
+        if let Some(capture) = self.capture.as_deref_mut() {
+            capture.record(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<'a, 'b, S> Drop for CaptureReader<'a, 'b, S> {
+    fn drop(&mut self) {
+        if let Some(capture) = self.capture.as_deref_mut() {
+            capture.end();
+        }
+    }
+}
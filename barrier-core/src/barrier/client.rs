@@ -0,0 +1,271 @@
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io_async::{Read, Write};
+
+use crate::barrier::{packet_stream::PacketStream, take::Take, PacketError};
+
+use super::{
+    Actuator, Capture, ClipboardMark, ConnectionError, Liveness, Options, Packet, PacketReader,
+    PacketWriter,
+};
+
+// Highest protocol version we speak, servers with the same major version and a higher minor
+// version fall back to ours.
+const PROTOCOL_MAJOR_VERSION: u16 = 1;
+const PROTOCOL_MINOR_VERSION: u16 = 6;
+
+// Synergy 1.x and the forks that kept its greeting say "Synergy", Barrier says "Barrier".
+// Both have the same length, the client echoes back whichever one the server used.
+const HELLO_LITERALS: [&str; 2] = ["Barrier", "Synergy"];
+
+// Same as Barrier, the server is considered dead after missing 3 keep alives
+const KEEP_ALIVES_UNTIL_DEATH: u64 = 3;
+const LIVENESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/**
+ * Keeps signalling liveness while waiting for the next packet, returns when the server has been
+ * silent for too long. A heartbeat of 0 disables the timeout.
+ */
+async fn wait_for_dead_server<L: Liveness>(
+    liveness: &mut L,
+    last_packet: Instant,
+    heartbeat_ms: u32,
+) {
+    loop {
+        liveness.feed();
+        let wait = if heartbeat_ms == 0 {
+            LIVENESS_INTERVAL
+        } else {
+            let deadline =
+                last_packet + Duration::from_millis(heartbeat_ms as u64 * KEEP_ALIVES_UNTIL_DEATH);
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            core::cmp::min(deadline - now, LIVENESS_INTERVAL)
+        };
+        Timer::after(wait).await;
+    }
+}
+
+/**
+ * Reads the server's greeting, anything after the version is skipped so the next packet starts
+ * where the greeting says it ends.
+ */
+async fn read_hello<S: PacketReader>(
+    stream: &mut S,
+) -> Result<(&'static str, u16, u16), PacketError> {
+    let size = stream.read_packet_size().await?;
+    let mut chunk = Take::new(stream, size as u64);
+    let hello: [u8; 7] = chunk
+        .read_bytes_fixed()
+        .await
+        .map_err(|e| chunk.map_eof(e))?;
+    let hello = HELLO_LITERALS
+        .iter()
+        .find(|lit| lit.as_bytes() == hello)
+        .ok_or(PacketError::FormatError)?;
+    let major = chunk.read_u16().await.map_err(|e| chunk.map_eof(e))?;
+    let minor = chunk.read_u16().await.map_err(|e| chunk.map_eof(e))?;
+    chunk.discard_all().await?;
+    Ok((hello, major, minor))
+}
+
+/**
+ * Runs the Barrier client over an established connection until the server goes away.
+ * The transport can be anything byte oriented, plain TCP, TLS, a serial port or an in-memory pipe.
+ */
+pub async fn start<S: Read + Write + Unpin, A: Actuator, L: Liveness>(
+    stream: S,
+    device_name: &str,
+    actor: &mut A,
+    liveness: &mut L,
+) -> Result<(), ConnectionError> {
+    run(stream, device_name, actor, liveness, None).await
+}
+
+/**
+ * Same as `start`, and records every packet the server sends into the capture.
+ */
+pub async fn start_with_capture<S: Read + Write + Unpin, A: Actuator, L: Liveness>(
+    stream: S,
+    device_name: &str,
+    actor: &mut A,
+    liveness: &mut L,
+    capture: &mut Capture<'_>,
+) -> Result<(), ConnectionError> {
+    run(stream, device_name, actor, liveness, Some(capture)).await
+}
+
+async fn run<S: Read + Write + Unpin, A: Actuator, L: Liveness>(
+    mut stream: S,
+    device_name: &str,
+    actor: &mut A,
+    liveness: &mut L,
+    capture: Option<&mut Capture<'_>>,
+) -> Result<(), ConnectionError> {
+    let screen_size: (u16, u16) = actor.get_screen_size().await;
+
+    // Turn off Nagle, this may not be available on ESP-IDF, so ignore the error.
+    // stream.set_nodelay(true).ok();
+
+    let (hello, major, minor) = read_hello(&mut stream).await?;
+    debug!("Got hello {} {}:{}", hello, major, minor);
+    if major != PROTOCOL_MAJOR_VERSION {
+        warn!("Incompatible server protocol version {}.{}", major, minor);
+        return Err(ConnectionError::IncompatibleVersion { major, minor });
+    }
+    let minor = core::cmp::min(minor, PROTOCOL_MINOR_VERSION);
+    debug!("Using protocol {}.{}", PROTOCOL_MAJOR_VERSION, minor);
+
+    stream
+        .write_u32(hello.len() as u32 + 2 + 2 + 4 + device_name.bytes().len() as u32)
+        .await?;
+    stream
+        .write_all(hello.as_bytes())
+        .await
+        .map_err(|_| PacketError::IoError)?;
+    stream.write_u16(PROTOCOL_MAJOR_VERSION).await?;
+    stream.write_u16(minor).await?;
+    stream.write_str(device_name).await?;
+    stream.flush().await.map_err(|_| PacketError::IoError)?;
+
+    actor.connected().await;
+
+    let packet_stream = match capture {
+        Some(capture) => PacketStream::with_capture(stream, capture),
+        None => PacketStream::new(stream),
+    };
+    let ret = process_packets(packet_stream, screen_size, actor, liveness).await;
+    actor.disconnected().await;
+    ret
+}
+
+async fn process_packets<S: PacketReader + PacketWriter, A: Actuator, L: Liveness>(
+    mut packet_stream: PacketStream<'_, '_, S>,
+    screen_size: (u16, u16),
+    actor: &mut A,
+    liveness: &mut L,
+) -> Result<(), ConnectionError> {
+    let mut options = Options::default();
+    let mut last_packet = Instant::now();
+    loop {
+        let packet = match select(
+            packet_stream.read(),
+            wait_for_dead_server(liveness, last_packet, options.heartbeat_ms),
+        )
+        .await
+        {
+            Either::First(packet) => packet?,
+            Either::Second(_) => {
+                warn!("Server missed {} keep alives", KEEP_ALIVES_UNTIL_DEATH);
+                return Err(ConnectionError::Timeout);
+            }
+        };
+        last_packet = Instant::now();
+        match packet {
+            Packet::QueryInfo => {
+                let (x, y) = actor.get_cursor_position().await;
+                packet_stream
+                    .write(Packet::DeviceInfo {
+                        x,
+                        y,
+                        w: screen_size.0,
+                        h: screen_size.1,
+                        _dummy: 0,
+                        mx: 0,
+                        my: 0,
+                    })
+                    .await?;
+            }
+            Packet::KeepAlive => {
+                packet_stream.write(Packet::KeepAlive).await?;
+            }
+            Packet::MouseMoveAbs { x, y } => {
+                actor.set_cursor_position(x, y).await;
+            }
+            Packet::MouseMove { x, y } => {
+                actor.move_cursor(x, y).await;
+            }
+            Packet::KeyUp { id, mask, button } => {
+                actor.key_up(id, mask, button).await;
+            }
+            Packet::KeyDown { id, mask, button } => {
+                actor.key_down(id, mask, button).await;
+            }
+            Packet::KeyRepeat {
+                id,
+                mask,
+                button,
+                count,
+            } => {
+                actor.key_repeat(id, mask, button, count).await;
+            }
+            Packet::MouseDown { id } => {
+                actor.mouse_down(id).await;
+            }
+            Packet::MouseUp { id } => {
+                actor.mouse_up(id).await;
+            }
+            Packet::MouseWheel { x_delta, y_delta } => {
+                actor.mouse_wheel(x_delta, y_delta).await;
+            }
+            Packet::InfoAck => { //Ignore
+            }
+            Packet::ResetOptions => {
+                options = Options::default();
+                actor.reset_options().await;
+            }
+            Packet::SetOptions(list) => {
+                options.apply(&list);
+                actor.set_options(options).await;
+            }
+            Packet::CursorEnter { x, y, mask, .. } => {
+                actor.enter(x, y, mask).await;
+            }
+            Packet::CursorLeave => {
+                actor.leave().await;
+            }
+            Packet::ClipboardData {
+                id,
+                mark: ClipboardMark::End,
+                ..
+            } => {
+                let clipboard = packet_stream.clipboard();
+                if clipboard.is_truncated() {
+                    debug!("Clipboard {} truncated", id);
+                }
+                if let Some(text) = clipboard.text() {
+                    actor.set_clipboard(id, text).await;
+                }
+            }
+            Packet::GrabClipboard { .. } | Packet::ClipboardData { .. } => {
+                // Chunks are collected by the packet stream
+            }
+            Packet::Close => {
+                info!("Server closed the connection");
+                return Ok(());
+            }
+            Packet::ErrorIncompatible { major, minor } => {
+                warn!("Server requires protocol {}.{}", major, minor);
+                return Err(ConnectionError::IncompatibleVersion { major, minor });
+            }
+            Packet::ErrorBusy => {
+                warn!("Screen name is already in use");
+                return Err(ConnectionError::NameInUse);
+            }
+            Packet::ErrorUnknownDevice => {
+                warn!("Screen name is unknown to the server");
+                return Err(ConnectionError::UnknownName);
+            }
+            Packet::ErrorBadMessage => {
+                warn!("Server rejected a message");
+                return Err(ConnectionError::BadMessage);
+            }
+            Packet::DeviceInfo { .. } | Packet::ClientNoOp => {
+                // Client only packets, a server never sends these
+            }
+            Packet::Unknown(_) => {}
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use core::str::from_utf8;
+
+use super::{PacketError, PacketReader};
+
+// Anything beyond this is dropped, the text at the front is still usable
+pub const CLIPBOARD_BUFFER_SIZE: usize = 2048;
+
+#[allow(dead_code)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+    Text = 0,
+    Html = 1,
+    Bitmap = 2,
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMark {
+    Start = 1,
+    Chunk = 2,
+    End = 3,
+}
+
+impl TryFrom<u8> for ClipboardMark {
+    type Error = PacketError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ClipboardMark::Start),
+            2 => Ok(ClipboardMark::Chunk),
+            3 => Ok(ClipboardMark::End),
+            _ => Err(PacketError::FormatError),
+        }
+    }
+}
+
+/**
+ * Reassembles the DCLP chunk stream into a bounded buffer.
+ * The server sends a start mark carrying the total size, a number of data chunks and an end mark,
+ * all tagged with the clipboard id and the sequence number of the last CINN.
+ */
+pub struct Clipboard {
+    id: u8,
+    seq_num: u32,
+    expected: usize,
+    received: usize,
+    len: usize,
+    receiving: bool,
+    complete: bool,
+    buf: [u8; CLIPBOARD_BUFFER_SIZE],
+}
+
+impl Clipboard {
+    pub const fn new() -> Self {
+        Self {
+            id: 0,
+            seq_num: 0,
+            expected: 0,
+            received: 0,
+            len: 0,
+            receiving: false,
+            complete: false,
+            buf: [0; CLIPBOARD_BUFFER_SIZE],
+        }
+    }
+
+    pub fn begin(&mut self, id: u8, seq_num: u32, expected: usize) {
+        debug!("Clipboard {} transfer started, {} bytes", id, expected);
+        if expected > self.buf.len() {
+            warn!(
+                "Clipboard data too large, keeping only the first {} bytes",
+                self.buf.len()
+            );
+        }
+        self.id = id;
+        self.seq_num = seq_num;
+        self.expected = expected;
+        self.received = 0;
+        self.len = 0;
+        self.receiving = true;
+        self.complete = false;
+    }
+
+    /**
+     * Reads a chunk of `len` bytes into the buffer, and returns the part of it that fit.
+     */
+    pub async fn append<R: PacketReader>(
+        &mut self,
+        id: u8,
+        seq_num: u32,
+        reader: &mut R,
+        len: usize,
+    ) -> Result<&[u8], PacketError> {
+        if !self.receiving || id != self.id || seq_num != self.seq_num {
+            warn!("Unexpected clipboard chunk for {}:{}", id, seq_num);
+            reader.consume_bytes(len).await?;
+            return Ok(&[]);
+        }
+        let start = self.len;
+        let to_keep = core::cmp::min(len, self.buf.len() - start);
+        reader
+            .read_exact(&mut self.buf[start..start + to_keep])
+            .await?;
+        reader.consume_bytes(len - to_keep).await?;
+        self.len += to_keep;
+        self.received += len;
+        Ok(&self.buf[start..self.len])
+    }
+
+    pub fn finish(&mut self, id: u8, seq_num: u32) -> bool {
+        if !self.receiving || id != self.id || seq_num != self.seq_num {
+            warn!("Unexpected clipboard end for {}:{}", id, seq_num);
+            return false;
+        }
+        if self.received != self.expected {
+            warn!(
+                "Clipboard size mismatch, expected {}, got {}",
+                self.expected, self.received
+            );
+        }
+        self.receiving = false;
+        self.complete = true;
+        true
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.received > self.len
+    }
+
+    /**
+     * Returns the data in the given format, truncated to what fits in the buffer.
+     * The payload is a u32 format count followed by (format: u32, size: u32, data) entries.
+     */
+    pub fn get(&self, format: ClipboardFormat) -> Option<&[u8]> {
+        if !self.complete {
+            return None;
+        }
+        let data = &self.buf[..self.len];
+        let count = read_u32(data, 0)?;
+        let mut pos = 4;
+        for _ in 0..count {
+            let fmt = read_u32(data, pos)?;
+            let size = read_u32(data, pos + 4)? as usize;
+            pos += 8;
+            let end = core::cmp::min(pos.saturating_add(size), data.len());
+            if fmt == format as u32 {
+                return Some(&data[pos..end]);
+            }
+            if end == data.len() {
+                break;
+            }
+            pos = end;
+        }
+        None
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        let data = self.get(ClipboardFormat::Text)?;
+        match from_utf8(data) {
+            Ok(s) => Some(s),
+            // Truncation may have split a multi-byte character, keep the valid prefix
+            Err(e) => from_utf8(&data[..e.valid_up_to()]).ok(),
+        }
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,58 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketError {
+    #[error("io error")]
+    IoError,
+    #[error("unexpected end of stream")]
+    Eof,
+    #[error("did not match format")]
+    FormatError,
+    #[error("Packet too small")]
+    PacketTooSmall,
+}
+
+impl<T> From<embedded_io_async::ReadExactError<T>> for PacketError {
+    fn from(e: embedded_io_async::ReadExactError<T>) -> Self {
+        match e {
+            embedded_io_async::ReadExactError::UnexpectedEof => PacketError::Eof,
+            embedded_io_async::ReadExactError::Other(_) => PacketError::IoError,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectionError {
+    #[error("tcp connection failed")]
+    TcpError,
+    #[error("tls handshake failed")]
+    TlsError,
+    #[error("connection closed by the server")]
+    Eof,
+    #[error("connection io error")]
+    IoError,
+    #[error("server stopped sending keep alives")]
+    Timeout,
+    #[error("server protocol version {major}.{minor} is incompatible")]
+    IncompatibleVersion { major: u16, minor: u16 },
+    #[error("screen name is already in use")]
+    NameInUse,
+    #[error("screen name is unknown to the server")]
+    UnknownName,
+    #[error("server rejected a message")]
+    BadMessage,
+    #[error("invalid data received")]
+    ProtocolError(PacketError),
+}
+
+impl From<PacketError> for ConnectionError {
+    fn from(e: PacketError) -> Self {
+        match e {
+            PacketError::Eof => ConnectionError::Eof,
+            PacketError::IoError => ConnectionError::IoError,
+            _ => ConnectionError::ProtocolError(e),
+        }
+    }
+}
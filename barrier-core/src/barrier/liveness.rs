@@ -0,0 +1,13 @@
+/**
+ * Signals that the client loop is still making progress, e.g. by feeding a hardware watchdog.
+ * It's called regularly while the client is waiting for the server.
+ */
+pub trait Liveness {
+    fn feed(&mut self);
+}
+
+impl<F: FnMut()> Liveness for F {
+    fn feed(&mut self) {
+        self()
+    }
+}
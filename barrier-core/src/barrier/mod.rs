@@ -0,0 +1,24 @@
+mod error;
+mod actuator;
+mod capture;
+mod clipboard;
+mod liveness;
+mod options;
+mod packet;
+mod packet_io;
+mod packet_stream;
+mod replay;
+mod take;
+mod client;
+
+pub use error::*;
+pub use packet::*;
+pub use packet_io::*;
+pub use packet_stream::PacketStream;
+pub use actuator::Actuator;
+pub use liveness::Liveness;
+pub use clipboard::*;
+pub use options::*;
+pub use capture::{Capture, RECORD_HEADER_SIZE};
+pub use replay::{replay, Replay};
+pub use client::{start, start_with_capture};
\ No newline at end of file
@@ -0,0 +1,128 @@
+
+const fn option_code(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+pub const OPTION_HALF_DUPLEX_CAPS_LOCK: u32 = option_code(b"HDCL");
+pub const OPTION_HALF_DUPLEX_NUM_LOCK: u32 = option_code(b"HDNL");
+pub const OPTION_HALF_DUPLEX_SCROLL_LOCK: u32 = option_code(b"HDSL");
+pub const OPTION_HEARTBEAT: u32 = option_code(b"HART");
+pub const OPTION_SCREEN_SAVER_SYNC: u32 = option_code(b"SSVR");
+pub const OPTION_RELATIVE_MOUSE_MOVES: u32 = option_code(b"MDLT");
+pub const OPTION_CLIPBOARD_SHARING: u32 = option_code(b"CLPS");
+
+// Barrier's kKeepAliveRate
+pub const DEFAULT_HEARTBEAT_MS: u32 = 3000;
+
+// The options `Options` knows, DSOP packets may carry others meant for other clients
+const KNOWN_OPTIONS: [u32; 7] = [
+    OPTION_HALF_DUPLEX_CAPS_LOCK,
+    OPTION_HALF_DUPLEX_NUM_LOCK,
+    OPTION_HALF_DUPLEX_SCROLL_LOCK,
+    OPTION_HEARTBEAT,
+    OPTION_SCREEN_SAVER_SYNC,
+    OPTION_RELATIVE_MOUSE_MOVES,
+    OPTION_CLIPBOARD_SHARING,
+];
+
+// Only known options are kept, each at most once, so this is plenty
+pub const MAX_OPTIONS: usize = 16;
+
+/**
+ * The (id, value) pairs of a DSOP packet, without the options `Options` doesn't know.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OptionList {
+    len: usize,
+    items: [(u32, u32); MAX_OPTIONS],
+}
+
+impl OptionList {
+    pub fn is_known(id: u32) -> bool {
+        KNOWN_OPTIONS.contains(&id)
+    }
+
+    /**
+     * Adds the option, a later value for the same id replaces the earlier one.
+     * False if the list is full.
+     */
+    pub fn push(&mut self, id: u32, value: u32) -> bool {
+        if let Some(item) = self.items[..self.len].iter_mut().find(|(i, _)| *i == id) {
+            item.1 = value;
+            return true;
+        }
+        if self.len >= MAX_OPTIONS {
+            return false;
+        }
+        self.items[self.len] = (id, value);
+        self.len += 1;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(u32, u32)> {
+        self.items[..self.len].iter()
+    }
+}
+
+/**
+ * Options set by the server with DSOP, CROP restores the defaults.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub heartbeat_ms: u32,
+    pub screen_saver_sync: bool,
+    pub half_duplex_caps_lock: bool,
+    pub half_duplex_num_lock: bool,
+    pub half_duplex_scroll_lock: bool,
+    pub relative_mouse_moves: bool,
+    pub clipboard_sharing: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            heartbeat_ms: DEFAULT_HEARTBEAT_MS,
+            screen_saver_sync: false,
+            half_duplex_caps_lock: false,
+            half_duplex_num_lock: false,
+            half_duplex_scroll_lock: false,
+            relative_mouse_moves: false,
+            clipboard_sharing: true,
+        }
+    }
+}
+
+impl Options {
+    pub fn set(&mut self, id: u32, value: u32) {
+        match id {
+            OPTION_HALF_DUPLEX_CAPS_LOCK => self.half_duplex_caps_lock = value != 0,
+            OPTION_HALF_DUPLEX_NUM_LOCK => self.half_duplex_num_lock = value != 0,
+            OPTION_HALF_DUPLEX_SCROLL_LOCK => self.half_duplex_scroll_lock = value != 0,
+            OPTION_HEARTBEAT => self.heartbeat_ms = value,
+            OPTION_SCREEN_SAVER_SYNC => self.screen_saver_sync = value != 0,
+            OPTION_RELATIVE_MOUSE_MOVES => self.relative_mouse_moves = value != 0,
+            OPTION_CLIPBOARD_SHARING => self.clipboard_sharing = value != 0,
+            _ => {
+                debug!(
+                    "Ignoring option {:?} = {}",
+                    core::str::from_utf8(&id.to_be_bytes()).unwrap_or("???"),
+                    value
+                );
+            }
+        }
+    }
+
+    pub fn apply(&mut self, list: &OptionList) {
+        for &(id, value) in list.iter() {
+            self.set(id, value);
+        }
+    }
+}
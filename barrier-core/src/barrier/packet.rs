@@ -0,0 +1,273 @@
+use embedded_io_async::Write;
+
+use super::{ClipboardMark, OptionList, PacketError, PacketWriter};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet<'a> {
+    QueryInfo,
+    DeviceInfo {
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        _dummy: u16,
+        mx: u16, // x position of the mouse on the secondary screen
+        my: u16, // y position of the mouse on the secondary screen
+    },
+    InfoAck,
+    KeepAlive,
+    ResetOptions,
+    SetOptions(OptionList),
+    ClientNoOp,
+    ErrorUnknownDevice,
+    ErrorIncompatible {
+        major: u16,
+        minor: u16,
+    },
+    ErrorBusy,
+    ErrorBadMessage,
+    Close,
+    CursorEnter {
+        x: u16,
+        y: u16,
+        seq_num: u32,
+        mask: u16,
+    },
+    MouseUp {
+        id: i8,
+    },
+    MouseDown {
+        id: i8,
+    },
+    KeyUp {
+        id: u16,
+        mask: u16,
+        button: u16,
+    },
+    KeyDown {
+        id: u16,
+        mask: u16,
+        button: u16,
+    },
+    KeyRepeat {
+        id: u16,
+        mask: u16,
+        button: u16,
+        count: u16,
+    },
+    MouseWheel {
+        x_delta: i16,
+        y_delta: i16,
+    },
+    CursorLeave,
+    MouseMoveAbs {
+        x: u16,
+        y: u16,
+    },
+    MouseMove {
+        x: i16,
+        y: i16,
+    },
+    GrabClipboard {
+        id: u8,
+        seq_num: u32,
+    },
+    // Start carries the total size in decimal, Chunk a part of the data and End nothing. Chunks
+    // read by `PacketStream` borrow `data` from its clipboard, so it's only the part that fit
+    ClipboardData {
+        id: u8,
+        seq_num: u32,
+        mark: ClipboardMark,
+        data: &'a [u8],
+    },
+    Unknown([u8; 4]),
+}
+
+impl Packet<'_> {
+    pub async fn write_wire<W: Write + Unpin>(self, mut out: W) -> Result<(), PacketError> {
+        match self {
+            Packet::QueryInfo => {
+                out.write_str("QINF").await?;
+                Ok(())
+            }
+            Packet::DeviceInfo {
+                x,
+                y,
+                w,
+                h,
+                _dummy,
+                mx,
+                my,
+            } => {
+                out.write_u32(2 * 7 + 4).await?;
+                out.write_all(b"DINF").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(x).await?;
+                out.write_u16(y).await?;
+                out.write_u16(w).await?;
+                out.write_u16(h).await?;
+                out.write_u16(_dummy).await?;
+                out.write_u16(mx).await?;
+                out.write_u16(my).await?;
+                Ok(())
+            }
+            Packet::ClientNoOp => {
+                out.write_str("CNOP").await?;
+                Ok(())
+            }
+            Packet::Unknown(code) => {
+                out.write_u32(4).await?;
+                out.write_all(&code).await.map_err(|_| PacketError::IoError)?;
+                Ok(())
+            }
+            Packet::InfoAck => {
+                out.write_str("CIAK").await?;
+                Ok(())
+            }
+            Packet::KeepAlive => {
+                out.write_str("CALV").await?;
+                Ok(())
+            }
+            Packet::ErrorUnknownDevice => {
+                out.write_str("EUNK").await?;
+                Ok(())
+            }
+            Packet::ErrorIncompatible { major, minor } => {
+                out.write_u32(4 + 2 + 2).await?;
+                out.write_all(b"EICV").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(major).await?;
+                out.write_u16(minor).await?;
+                Ok(())
+            }
+            Packet::ErrorBusy => {
+                out.write_str("EBSY").await?;
+                Ok(())
+            }
+            Packet::ErrorBadMessage => {
+                out.write_str("EBAD").await?;
+                Ok(())
+            }
+            Packet::Close => {
+                out.write_str("CBYE").await?;
+                Ok(())
+            }
+            Packet::ResetOptions => {
+                out.write_str("CROP").await?;
+                Ok(())
+            }
+            Packet::SetOptions(options) => {
+                out.write_u32(4 + 4 + 4 * 2 * options.len() as u32).await?;
+                out.write_all(b"DSOP").await.map_err(|_| PacketError::IoError)?;
+                out.write_u32(2 * options.len() as u32).await?;
+                for &(id, value) in options.iter() {
+                    out.write_u32(id).await?;
+                    out.write_u32(value).await?;
+                }
+                Ok(())
+            }
+            Packet::CursorEnter {
+                x,
+                y,
+                seq_num,
+                mask,
+            } => {
+                out.write_u32(4 + 2 + 2 + 4 + 2).await?;
+                out.write_all(b"CINN").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(x).await?;
+                out.write_u16(y).await?;
+                out.write_u32(seq_num).await?;
+                out.write_u16(mask).await?;
+                Ok(())
+            }
+            Packet::CursorLeave => {
+                out.write_str("COUT").await?;
+                Ok(())
+            }
+            Packet::MouseUp { id } => {
+                out.write_u32(4 + 1).await?;
+                out.write_all(b"DMUP").await.map_err(|_| PacketError::IoError)?;
+                out.write_i8(id).await?;
+                Ok(())
+            }
+            Packet::MouseDown { id } => {
+                out.write_u32(4 + 1).await?;
+                out.write_all(b"DMDN").await.map_err(|_| PacketError::IoError)?;
+                out.write_i8(id).await?;
+                Ok(())
+            }
+            Packet::KeyUp { id, mask, button } => {
+                out.write_u32(4 + 2 + 2 + 2).await?;
+                out.write_all(b"DKUP").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(id).await?;
+                out.write_u16(mask).await?;
+                out.write_u16(button).await?;
+                Ok(())
+            }
+            Packet::KeyDown { id, mask, button } => {
+                out.write_u32(4 + 2 + 2 + 2).await?;
+                out.write_all(b"DKDN").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(id).await?;
+                out.write_u16(mask).await?;
+                out.write_u16(button).await?;
+                Ok(())
+            }
+            Packet::KeyRepeat {
+                id,
+                mask,
+                button,
+                count,
+            } => {
+                out.write_u32(4 + 2 + 2 + 2 + 2).await?;
+                out.write_all(b"DKRP").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(id).await?;
+                out.write_u16(mask).await?;
+                out.write_u16(count).await?;
+                out.write_u16(button).await?;
+                Ok(())
+            }
+            Packet::MouseWheel { x_delta, y_delta } => {
+                out.write_u32(4 + 2 + 2).await?;
+                out.write_all(b"DMWM").await.map_err(|_| PacketError::IoError)?;
+                out.write_i16(x_delta).await?;
+                out.write_i16(y_delta).await?;
+                Ok(())
+            }
+            Packet::MouseMoveAbs { x, y } => {
+                out.write_u32(4 + 2 + 2).await?;
+                out.write_all(b"DMMV").await.map_err(|_| PacketError::IoError)?;
+                out.write_u16(x).await?;
+                out.write_u16(y).await?;
+                Ok(())
+            }
+            Packet::MouseMove { x, y } => {
+                out.write_u32(4 + 2 + 2).await?;
+                out.write_all(b"DMRM").await.map_err(|_| PacketError::IoError)?;
+                out.write_i16(x).await?;
+                out.write_i16(y).await?;
+                Ok(())
+            }
+            Packet::GrabClipboard { id, seq_num } => {
+                out.write_u32(4 + 1 + 4).await?;
+                out.write_all(b"CCLP").await.map_err(|_| PacketError::IoError)?;
+                out.write_u8(id).await?;
+                out.write_u32(seq_num).await?;
+                Ok(())
+            }
+            Packet::ClipboardData {
+                id,
+                seq_num,
+                mark,
+                data,
+            } => {
+                out.write_u32(4 + 1 + 4 + 1 + 4 + data.len() as u32).await?;
+                out.write_all(b"DCLP").await.map_err(|_| PacketError::IoError)?;
+                out.write_u8(id).await?;
+                out.write_u32(seq_num).await?;
+                out.write_u8(mark as u8).await?;
+                out.write_u32(data.len() as u32).await?;
+                out.write_all(data).await.map_err(|_| PacketError::IoError)?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -13,23 +13,14 @@ pub trait PacketReader: Read + Unpin {
         Ok(())
     }
 
-    async fn discard_exact(&mut self, len: usize) -> Result<(), PacketError> {
-        let mut buf = [0; 16];
-        let mut len = len;
-        while len > 0 {
-            let to_read = core::cmp::min(len, buf.len());
-            self.read_exact(&mut buf[..to_read]).await?;
-            len -= to_read;
-        }
-        Ok(())
-    }
-
     async fn read_str_lit(&mut self, lit: &str) -> Result<(), PacketError> {
-        // TODO: Longer than 16 bytes
         let mut buf = [0u8; 16];
-        self.read_exact(&mut buf[..lit.len()]).await?;
-        if &buf[..lit.len()] != lit.as_bytes() {
-            return Err(PacketError::FormatError);
+        for expected in lit.as_bytes().chunks(buf.len()) {
+            let buf = &mut buf[..expected.len()];
+            self.read_exact(buf).await?;
+            if buf != expected {
+                return Err(PacketError::FormatError);
+            }
         }
         Ok(())
     }
@@ -86,6 +77,24 @@ pub trait PacketWriter: Write + Unpin {
         Ok(())
     }
 
+    async fn write_i8(&mut self, data: i8) -> Result<(), PacketError> {
+        self.write_all(&data.to_be_bytes())
+            .await
+            .map_err(|_| PacketError::IoError)
+    }
+
+    async fn write_u8(&mut self, data: u8) -> Result<(), PacketError> {
+        self.write_all(&data.to_be_bytes())
+            .await
+            .map_err(|_| PacketError::IoError)
+    }
+
+    async fn write_i16(&mut self, data: i16) -> Result<(), PacketError> {
+        self.write_all(&data.to_be_bytes())
+            .await
+            .map_err(|_| PacketError::IoError)
+    }
+
     async fn write_u16(&mut self, data: u16) -> Result<(), PacketError> {
         self.write_all(&data.to_be_bytes())
             .await
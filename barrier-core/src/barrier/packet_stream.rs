@@ -0,0 +1,273 @@
+use core::str::from_utf8;
+use embassy_time::Instant;
+
+use super::{
+    capture::CaptureReader, Capture, Clipboard, ClipboardMark, OptionList, Packet, PacketError,
+    PacketReader, PacketWriter,
+};
+
+pub struct PacketStream<'a, 'b, S: PacketReader + PacketWriter> {
+    stream: S,
+    clipboard: Clipboard,
+    // Data of the last clipboard start mark, the total size in decimal
+    clipboard_size: [u8; 16],
+    capture: Option<&'a mut Capture<'b>>,
+}
+
+impl<'a, 'b, S: PacketReader + PacketWriter> PacketStream<'a, 'b, S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            clipboard: Clipboard::new(),
+            clipboard_size: [0; 16],
+            capture: None,
+        }
+    }
+
+    /**
+     * Records every frame read from the stream into the capture.
+     */
+    pub fn with_capture(stream: S, capture: &'a mut Capture<'b>) -> Self {
+        Self {
+            stream,
+            clipboard: Clipboard::new(),
+            clipboard_size: [0; 16],
+            capture: Some(capture),
+        }
+    }
+
+    pub fn clipboard(&self) -> &Clipboard {
+        &self.clipboard
+    }
+
+    /**
+     * Reads the next packet, the data of clipboard packets borrows from the stream.
+     */
+    pub async fn read(&mut self) -> Result<Packet<'_>, PacketError> {
+        let size = self.stream.read_packet_size().await?;
+        if let Some(capture) = self.capture.as_deref_mut() {
+            capture.begin(
+                Instant::now().as_millis() as u32,
+                (size as usize).saturating_add(4),
+            );
+            capture.record(&size.to_be_bytes());
+        }
+        if size < 4 {
+            return Err(PacketError::PacketTooSmall);
+        }
+        let mut reader = CaptureReader::new(&mut self.stream, self.capture.as_deref_mut());
+        let mut chunk = super::take::Take::new(&mut reader, size as u64);
+        let code: [u8; 4] = chunk
+            .read_bytes_fixed()
+            .await
+            .map_err(|e| chunk.map_eof(e))?;
+        debug!("Got packet {:?}", from_utf8(&code).unwrap_or("???"));
+        // Clipboard chunks are streamed into the clipboard buffer instead
+        if size > 2048 && &code != b"DCLP" {
+            warn!("Packet too large, discarding {} bytes", size);
+            chunk.discard_all().await?;
+            return Ok(Packet::Unknown(code));
+        }
+
+        let packet = read_body(
+            &code,
+            &mut chunk,
+            &mut self.clipboard,
+            &mut self.clipboard_size,
+        )
+        .await
+        .map_err(|e| chunk.map_eof(e))?;
+
+        // Discard the rest of the packet
+        if chunk.limit() > 0 {
+            warn!(
+                "Discarding rest of packet, code: {:?}, size: {}",
+                from_utf8(&code).unwrap_or("???"),
+                chunk.limit()
+            );
+            chunk.discard_all().await?;
+        }
+
+        Ok(packet)
+    }
+
+    pub async fn write(&mut self, packet: Packet<'_>) -> Result<(), PacketError> {
+        packet.write_wire(&mut self.stream).await?;
+        // Buffered transports like TLS only send on flush
+        self.stream
+            .flush()
+            .await
+            .map_err(|_| PacketError::IoError)?;
+        Ok(())
+    }
+}
+
+/**
+ * Parses the fields of the packet, none of the reads can go past the end of the chunk.
+ */
+async fn read_body<'c, R: PacketReader>(
+    code: &[u8; 4],
+    chunk: &mut R,
+    clipboard: &'c mut Clipboard,
+    clipboard_size: &'c mut [u8; 16],
+) -> Result<Packet<'c>, PacketError> {
+    let packet = match code {
+        b"QINF" => Packet::QueryInfo,
+        b"CIAK" => Packet::InfoAck,
+        b"CALV" => Packet::KeepAlive,
+        b"CNOP" => Packet::ClientNoOp,
+        b"CBYE" => Packet::Close,
+        b"CROP" => Packet::ResetOptions,
+        b"DSOP" => {
+            // A list of u32, alternating option id and value
+            let count = chunk.read_u32().await?;
+            if count % 2 != 0 {
+                return Err(PacketError::FormatError);
+            }
+            let mut options = OptionList::default();
+            for _ in 0..count / 2 {
+                let id = chunk.read_u32().await?;
+                let value = chunk.read_u32().await?;
+                // Unknown options would take the room of the ones that matter
+                if !OptionList::is_known(id) {
+                    debug!(
+                        "Ignoring option {:?} = {}",
+                        from_utf8(&id.to_be_bytes()).unwrap_or("???"),
+                        value
+                    );
+                    continue;
+                }
+                if !options.push(id, value) {
+                    warn!("Too many options, ignoring {}", id);
+                }
+            }
+            Packet::SetOptions(options)
+        }
+        b"EUNK" => Packet::ErrorUnknownDevice,
+        b"EICV" => {
+            let major = chunk.read_u16().await?;
+            let minor = chunk.read_u16().await?;
+            Packet::ErrorIncompatible { major, minor }
+        }
+        b"EBSY" => Packet::ErrorBusy,
+        b"EBAD" => Packet::ErrorBadMessage,
+        b"DINF" => {
+            let x = chunk.read_u16().await?;
+            let y = chunk.read_u16().await?;
+            let w = chunk.read_u16().await?;
+            let h = chunk.read_u16().await?;
+            let _dummy = chunk.read_u16().await?;
+            let mx = chunk.read_u16().await?;
+            let my = chunk.read_u16().await?;
+            Packet::DeviceInfo {
+                x,
+                y,
+                w,
+                h,
+                _dummy,
+                mx,
+                my,
+            }
+        }
+        b"DMMV" => {
+            let x = chunk.read_u16().await?;
+            let y = chunk.read_u16().await?;
+            Packet::MouseMoveAbs { x, y }
+        }
+        b"DMRM" => {
+            let x = chunk.read_i16().await?;
+            let y = chunk.read_i16().await?;
+            Packet::MouseMove { x, y }
+        }
+        b"CINN" => {
+            let x = chunk.read_u16().await?;
+            let y = chunk.read_u16().await?;
+            let seq_num = chunk.read_u32().await?;
+            let mask = chunk.read_u16().await?;
+            Packet::CursorEnter {
+                x,
+                y,
+                seq_num,
+                mask,
+            }
+        }
+        b"COUT" => Packet::CursorLeave,
+        b"DMUP" => {
+            let id = chunk.read_i8().await?;
+            Packet::MouseUp { id }
+        }
+        b"DMDN" => {
+            let id = chunk.read_i8().await?;
+            Packet::MouseDown { id }
+        }
+        b"DKUP" => {
+            let id = chunk.read_u16().await?;
+            let mask = chunk.read_u16().await?;
+            let button = chunk.read_u16().await?;
+            Packet::KeyUp { id, mask, button }
+        }
+        b"DKDN" => {
+            let id = chunk.read_u16().await?;
+            let mask = chunk.read_u16().await?;
+            let button = chunk.read_u16().await?;
+            Packet::KeyDown { id, mask, button }
+        }
+        b"DKRP" => {
+            let id = chunk.read_u16().await?;
+            let mask = chunk.read_u16().await?;
+            let count = chunk.read_u16().await?;
+            let button = chunk.read_u16().await?;
+            Packet::KeyRepeat {
+                id,
+                mask,
+                button,
+                count,
+            }
+        }
+        b"DMWM" => {
+            let x_delta = chunk.read_i16().await?;
+            let y_delta = chunk.read_i16().await?;
+            Packet::MouseWheel { x_delta, y_delta }
+        }
+        b"CCLP" => {
+            let id = chunk.read_u8().await?;
+            let seq_num = chunk.read_u32().await?;
+            Packet::GrabClipboard { id, seq_num }
+        }
+        b"DCLP" => {
+            let id = chunk.read_u8().await?;
+            let seq_num = chunk.read_u32().await?;
+            let mark = ClipboardMark::try_from(chunk.read_u8().await?)?;
+            let len = chunk.read_u32().await? as usize;
+            let data: &[u8] = match mark {
+                ClipboardMark::Start => {
+                    // The start mark carries the total size as a decimal string
+                    let buf = clipboard_size
+                        .get_mut(..len)
+                        .ok_or(PacketError::FormatError)?;
+                    chunk.read_exact(buf).await?;
+                    let expected = from_utf8(buf)
+                        .ok()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or(PacketError::FormatError)?;
+                    clipboard.begin(id, seq_num, expected);
+                    buf
+                }
+                ClipboardMark::Chunk => clipboard.append(id, seq_num, chunk, len).await?,
+                ClipboardMark::End => {
+                    chunk.consume_bytes(len).await?;
+                    clipboard.finish(id, seq_num);
+                    &[]
+                }
+            };
+            Packet::ClipboardData {
+                id,
+                seq_num,
+                mark,
+                data,
+            }
+        }
+        _ => Packet::Unknown(*code),
+    };
+    Ok(packet)
+}
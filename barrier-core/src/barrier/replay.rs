@@ -0,0 +1,92 @@
+use core::convert::Infallible;
+use embedded_io_async::{ErrorType, Read, Write};
+
+use super::{start, Actuator, ConnectionError, RECORD_HEADER_SIZE};
+
+// Captures start after the greeting, replays pretend the server is Barrier 1.6
+const HELLO: &[u8] = b"\x00\x00\x00\x0bBarrier\x00\x01\x00\x06";
+
+/**
+ * Transport that plays back an exported capture as if the server sent it, as fast as the client
+ * reads it. What the client writes is discarded. Truncated frames are skipped as they can't be
+ * parsed.
+ */
+pub struct Replay<'a> {
+    hello: &'static [u8],
+    records: &'a [u8],
+    frame: &'a [u8],
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(capture: &'a [u8]) -> Self {
+        Self {
+            hello: HELLO,
+            records: capture,
+            frame: &[],
+        }
+    }
+
+    // Moves to the next complete frame, false at the end of the capture
+    fn next_frame(&mut self) -> bool {
+        loop {
+            let Some(header) = self.records.get(..RECORD_HEADER_SIZE) else {
+                return false;
+            };
+            let len = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let Some(frame) = self
+                .records
+                .get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE.saturating_add(len))
+            else {
+                return false;
+            };
+            self.records = &self.records[RECORD_HEADER_SIZE + len..];
+            if len >= 4
+                && u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]) as usize + 4 == len
+            {
+                self.frame = frame;
+                return true;
+            }
+            warn!("Skipping truncated frame, {} bytes", len);
+        }
+    }
+}
+
+impl ErrorType for Replay<'_> {
+    type Error = Infallible;
+}
+
+impl Read for Replay<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        if !self.hello.is_empty() {
+            let n = core::cmp::min(buf.len(), self.hello.len());
+            buf[..n].copy_from_slice(&self.hello[..n]);
+            self.hello = &self.hello[n..];
+            return Ok(n);
+        }
+        if self.frame.is_empty() && !self.next_frame() {
+            return Ok(0);
+        }
+        let n = core::cmp::min(buf.len(), self.frame.len());
+        buf[..n].copy_from_slice(&self.frame[..n]);
+        self.frame = &self.frame[n..];
+        Ok(n)
+    }
+}
+
+impl Write for Replay<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        Ok(buf.len())
+    }
+}
+
+/**
+ * Feeds an exported capture through the client into the actuator.
+ * Captures usually end in the middle of a session, so the client sees the connection closing.
+ */
+pub async fn replay<A: Actuator>(
+    capture: &[u8],
+    device_name: &str,
+    actor: &mut A,
+) -> Result<(), ConnectionError> {
+    start(Replay::new(capture), device_name, actor, &mut || {}).await
+}
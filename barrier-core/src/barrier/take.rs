@@ -1,6 +1,8 @@
 use embedded_io_async::ErrorType;
 use embedded_io_async::Read;
 
+use super::PacketError;
+
 /**
  * A wrapper around a `Read` that limits the number of bytes that can be read.
  * Modified from std::io::Take as it needs to move the inner reader.
@@ -19,12 +21,29 @@ impl<'a, T: Read> Take<'a, T> {
         self.limit
     }
 
-    pub async fn discard_all(&mut self) -> Result<(), T::Error> {
+    /**
+     * Reading past the limit looks like the end of the stream, but it means the packet is shorter
+     * than its fields say, not that the connection was closed.
+     */
+    pub fn map_eof(&self, e: PacketError) -> PacketError {
+        match e {
+            PacketError::Eof if self.limit == 0 => PacketError::PacketTooSmall,
+            e => e,
+        }
+    }
+
+    /**
+     * Skips to the limit, the stream ending before it is an error like with `read_exact`.
+     */
+    pub async fn discard_all(&mut self) -> Result<(), PacketError> {
         let mut buf = [0; 1024];
         while self.limit > 0 {
-            let n = self.read(&mut buf).await?;
+            let n = self
+                .read(&mut buf)
+                .await
+                .map_err(|_| PacketError::IoError)?;
             if n == 0 {
-                break;
+                return Err(PacketError::Eof);
             }
         }
         Ok(())
@@ -0,0 +1,305 @@
+use std::{
+    future::Future,
+    io::{self, Read as _, Write as _},
+    net::TcpStream,
+    pin::pin,
+    process::ExitCode,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use barrier_core::{
+    barrier::{self, Capture, Options},
+    hid_actuator::{HidActuator, ReportSink},
+    synergy_hid::{ReportType, SynergyHid},
+};
+use embassy_time::{Duration, Timer};
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+
+const USAGE: &str =
+    "Usage: barrier-sim [--capture <file>] <server:port> <screen-name> [width height]
+       barrier-sim --replay <file> [width height]";
+
+// Enough for a long session, the oldest packets are dropped when it's full
+const CAPTURE_SIZE: usize = 1024 * 1024;
+
+// How long to wait before trying a socket again when it has nothing for us
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+const MODIFIER_NAMES: [&str; 8] = [
+    "LCtrl", "LShift", "LAlt", "LGui", "RCtrl", "RShift", "RAlt", "RGui",
+];
+
+/**
+ * Non-blocking std TCP stream, reads and writes that would block are retried after a short
+ * timer so the client's keep alive timeout keeps running.
+ */
+struct TcpTransport(TcpStream);
+
+impl ErrorType for TcpTransport {
+    type Error = ErrorKind;
+}
+
+impl Read for TcpTransport {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        loop {
+            match self.0.read(buf) {
+                Ok(len) => return Ok(len),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    Timer::after(POLL_INTERVAL).await
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    eprintln!("Read failed: {}", e);
+                    return Err(ErrorKind::Other);
+                }
+            }
+        }
+    }
+}
+
+impl Write for TcpTransport {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        loop {
+            match self.0.write(buf) {
+                Ok(len) => return Ok(len),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    Timer::after(POLL_INTERVAL).await
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    eprintln!("Write failed: {}", e);
+                    return Err(ErrorKind::Other);
+                }
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), ErrorKind> {
+        self.0.flush().map_err(|_| ErrorKind::Other)
+    }
+}
+
+/**
+ * Prints the HID reports the shared actuator makes of the Barrier events, and the events that
+ * don't make any, instead of sending them to a USB host.
+ */
+struct Printer;
+
+// Keyboard reports are the boot report or the NKRO bitmap, mouse reports the absolute, relative
+// or boot one, they are told apart by their size
+fn print_report(report: (ReportType, &[u8])) {
+    let data = report.1;
+    match report.0 {
+        ReportType::Keyboard => {
+            let modifiers: Vec<&str> = MODIFIER_NAMES
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| data[0] & (1 << i) != 0)
+                .map(|(_, name)| *name)
+                .collect();
+            let keys: Vec<u8> = if data.len() == 8 {
+                data[2..].iter().copied().filter(|k| *k != 0).collect()
+            } else {
+                (0..(data.len() - 1) * 8)
+                    .filter(|usage| data[1 + usage / 8] & (1 << (usage % 8)) != 0)
+                    .map(|usage| usage as u8)
+                    .collect()
+            };
+            println!(
+                "    keyboard {:02X?}  modifiers: [{}]  keys: {:02X?}",
+                data,
+                modifiers.join(" "),
+                keys
+            );
+        }
+        ReportType::Mouse if data.len() == 7 => {
+            println!(
+                "    mouse    {:02X?}  buttons: {:05b}  x: {}  y: {}  wheel: {}  pan: {}",
+                data,
+                data[0],
+                u16::from_le_bytes([data[1], data[2]]),
+                u16::from_le_bytes([data[3], data[4]]),
+                data[5] as i8,
+                data[6] as i8
+            );
+        }
+        ReportType::Mouse => {
+            let wheel = data.get(3).map_or(0, |w| *w as i8);
+            let pan = data.get(4).map_or(0, |p| *p as i8);
+            println!(
+                "    mouse    {:02X?}  buttons: {:05b}  dx: {}  dy: {}  wheel: {}  pan: {}",
+                data, data[0], data[1] as i8, data[2] as i8, wheel, pan
+            );
+        }
+        ReportType::Consumer => {
+            println!(
+                "    consumer {:02X?}  usage: {:#06X}",
+                data,
+                u16::from_le_bytes([data[0], data[1]])
+            );
+        }
+    }
+}
+
+impl ReportSink for Printer {
+    async fn send_report(&mut self, report: (ReportType, &[u8])) {
+        print_report(report);
+    }
+
+    async fn connected(&mut self) {
+        println!("Connected");
+    }
+
+    async fn disconnected(&mut self) {
+        println!("Disconnected");
+    }
+
+    async fn key_repeat(&mut self, key: u16, mask: u16, button: u16, count: u16) {
+        println!(
+            "Key repeat {:#06X} mask {:#06X} button {} count {}",
+            key, mask, button, count
+        );
+    }
+
+    async fn set_clipboard(&mut self, id: u8, text: &str) {
+        println!("Clipboard {}: {:?}", id, text);
+    }
+
+    async fn set_options(&mut self, options: Options) {
+        println!("Options {:?}", options);
+    }
+
+    async fn reset_options(&mut self) {
+        println!("Options reset");
+    }
+
+    async fn enter(&mut self, x: u16, y: u16, mask: u16) {
+        println!("Entered at {}, {} mask {:#06X}", x, y, mask);
+    }
+
+    async fn leave(&mut self) {
+        println!("Left");
+    }
+}
+
+fn printing_actuator(width: u16, height: u16) -> HidActuator<Printer> {
+    HidActuator::new(width, height, SynergyHid::new(false), Printer)
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Runs the future on this thread, parking it until the future is woken
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(ret) = fut.as_mut().poll(&mut cx) {
+            return ret;
+        }
+        thread::park();
+    }
+}
+
+fn parse_screen_size(args: &[String]) -> Option<(u16, u16)> {
+    match args {
+        [] => Some((1920, 1080)),
+        [width, height] => Some((width.parse().ok()?, height.parse().ok()?)),
+        _ => None,
+    }
+}
+
+fn connect(
+    endpoint: &str,
+    screen_name: &str,
+    width: u16,
+    height: u16,
+    capture_path: Option<&str>,
+) -> ExitCode {
+    println!("Connecting to {} as {}", endpoint, screen_name);
+    let socket = match TcpStream::connect(endpoint) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Failed to connect: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    socket.set_nodelay(true).ok();
+    if let Err(e) = socket.set_nonblocking(true) {
+        eprintln!("Failed to set up the socket: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let mut actuator = printing_actuator(width, height);
+    let mut capture_buffer = vec![
+        0;
+        if capture_path.is_some() {
+            CAPTURE_SIZE
+        } else {
+            0
+        }
+    ];
+    let mut capture = Capture::new(&mut capture_buffer);
+    let ret = block_on(barrier::start_with_capture(
+        TcpTransport(socket),
+        screen_name,
+        &mut actuator,
+        &mut || {},
+        &mut capture,
+    ));
+    if let Some(path) = capture_path {
+        let mut exported = vec![0; capture.len()];
+        block_on(capture.export(exported.as_mut_slice())).ok();
+        match std::fs::write(path, &exported) {
+            Ok(()) => println!("Saved {} bytes of capture to {}", exported.len(), path),
+            Err(e) => eprintln!("Failed to save the capture: {}", e),
+        }
+    }
+    match ret {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Connection failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn replay(path: &str, width: u16, height: u16) -> ExitCode {
+    let capture = match std::fs::read(path) {
+        Ok(capture) => capture,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut actuator = printing_actuator(width, height);
+    // Captures end without the server closing the connection, so the error is expected
+    block_on(barrier::replay(&capture, "replay", &mut actuator)).ok();
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let ret = match args.as_slice() {
+        [flag, path, size @ ..] if flag == "--replay" => {
+            parse_screen_size(size).map(|(width, height)| replay(path, width, height))
+        }
+        [flag, path, endpoint, name, size @ ..] if flag == "--capture" => parse_screen_size(size)
+            .map(|(width, height)| connect(endpoint, name, width, height, Some(path))),
+        [endpoint, name, size @ ..] => parse_screen_size(size)
+            .map(|(width, height)| connect(endpoint, name, width, height, None)),
+        _ => None,
+    };
+    ret.unwrap_or_else(|| {
+        eprintln!("{}", USAGE);
+        ExitCode::FAILURE
+    })
+}
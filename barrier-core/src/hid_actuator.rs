@@ -0,0 +1,305 @@
+use crate::{
+    barrier::{Actuator, Options},
+    synergy_hid::{split_move, KeyboardLeds, Protocol, ReportType, SynergyHid, MAX_REPORT_SIZE},
+};
+
+const KEY_CAPS_LOCK: u16 = 0xEFE5;
+const KEY_NUM_LOCK: u16 = 0xEF7F;
+const KEY_SCROLL_LOCK: u16 = 0xEF14;
+
+/**
+ * Where `HidActuator` sends the reports it makes, with the state of the host and hooks for the
+ * Barrier events that don't make reports. The hooks run after the reports of the event were sent.
+ */
+pub trait ReportSink {
+    #[must_use]
+    async fn send_report(&mut self, report: (ReportType, &[u8]));
+
+    // Protocols the host picked for the keyboard and mouse interfaces
+    fn keyboard_protocol(&self) -> Protocol {
+        Protocol::Report
+    }
+
+    fn mouse_protocol(&self) -> Protocol {
+        Protocol::Report
+    }
+
+    // Resolution Multiplier feature report the host set
+    fn resolution_multiplier(&self) -> u8 {
+        0
+    }
+
+    // Keyboard LEDs the host set, without a host the lock keys are assumed to be off
+    fn keyboard_leds(&self) -> KeyboardLeds {
+        KeyboardLeds::default()
+    }
+
+    /**
+     * Called before a key press turns into reports, returning true takes the key away from the
+     * host, its release is dropped as well.
+     */
+    #[must_use]
+    async fn take_key(&mut self, _key: u16, _mask: u16, _button: u16) -> bool {
+        false
+    }
+
+    #[must_use]
+    async fn connected(&mut self) {}
+
+    #[must_use]
+    async fn disconnected(&mut self) {}
+
+    #[must_use]
+    async fn key_repeat(&mut self, _key: u16, _mask: u16, _button: u16, _count: u16) {}
+
+    #[must_use]
+    async fn set_clipboard(&mut self, _id: u8, _text: &str) {}
+
+    #[must_use]
+    async fn set_options(&mut self, _options: Options) {}
+
+    #[must_use]
+    async fn reset_options(&mut self) {}
+
+    #[must_use]
+    async fn enter(&mut self, _x: u16, _y: u16, _mask: u16) {}
+
+    #[must_use]
+    async fn leave(&mut self) {}
+}
+
+/**
+ * Turns Barrier events into HID reports with `SynergyHid` and hands them to a `ReportSink`.
+ */
+pub struct HidActuator<S> {
+    width: u16,
+    height: u16,
+    x: u16,
+    y: u16,
+    hid: SynergyHid,
+    options: Options,
+    // Button of the key the sink took, its key up doesn't reach the host either
+    taken_button: Option<u16>,
+    sink: S,
+}
+
+impl<S: ReportSink> HidActuator<S> {
+    pub fn new(width: u16, height: u16, hid: SynergyHid, sink: S) -> Self {
+        Self {
+            width,
+            height,
+            x: 0,
+            y: 0,
+            hid,
+            options: Options::default(),
+            taken_button: None,
+            sink,
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    // Keyboard and mouse modes, layout and the other settings of the reports
+    pub fn hid_mut(&mut self) -> &mut SynergyHid {
+        &mut self.hid
+    }
+
+    pub fn scale_position(&self, x: u16, y: u16) -> (u16, u16) {
+        // Scale screen position to HID position
+        (
+            ((x as f32) * (0x7fff as f32) / (self.width as f32)) as u16,
+            ((y as f32) * (0x7fff as f32) / (self.height as f32)) as u16,
+        )
+    }
+
+    // The host switches to the boot protocol when it reboots into its firmware setup, and turns on
+    // high resolution scrolling after enumerating the mouse
+    fn hid(&mut self) -> &mut SynergyHid {
+        self.hid
+            .set_keyboard_protocol(self.sink.keyboard_protocol());
+        self.hid.set_mouse_protocol(self.sink.mouse_protocol());
+        self.hid
+            .set_resolution_multiplier(self.sink.resolution_multiplier());
+        &mut self.hid
+    }
+
+    // With half-duplex lock keys the server sends key down when the lock turns on and key up when it
+    // turns off, the host needs a full press and release for each of them.
+    fn is_half_duplex(&self, key: u16) -> bool {
+        match key {
+            KEY_CAPS_LOCK => self.options.half_duplex_caps_lock,
+            KEY_NUM_LOCK => self.options.half_duplex_num_lock,
+            KEY_SCROLL_LOCK => self.options.half_duplex_scroll_lock,
+            _ => false,
+        }
+    }
+
+    async fn release_all(&mut self) {
+        self.taken_button = None;
+        let mut report = [0; MAX_REPORT_SIZE];
+        for report_type in [
+            ReportType::Keyboard,
+            ReportType::Mouse,
+            ReportType::Consumer,
+        ] {
+            let ret = self.hid().clear(report_type, &mut report);
+            self.sink.send_report(ret).await;
+        }
+    }
+
+    async fn tap_key(&mut self, key: u16, mask: u16, button: u16) {
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().key_down(key, mask, button, &mut report);
+        self.sink.send_report(ret).await;
+        let ret = self.hid().key_up(key, mask, button, &mut report);
+        self.sink.send_report(ret).await;
+    }
+
+    // Relative reports only carry i8 deltas, longer moves take several of them
+    async fn move_by(&mut self, x: i32, y: i32) {
+        let mut report = [0; MAX_REPORT_SIZE];
+        for (dx, dy) in split_move(x, y) {
+            let ret = self.hid().mouse_move(dx, dy, &mut report);
+            self.sink.send_report(ret).await;
+        }
+    }
+}
+
+impl<S: ReportSink> Actuator for HidActuator<S> {
+    async fn connected(&mut self) {
+        self.sink.connected().await;
+    }
+
+    async fn disconnected(&mut self) {
+        self.release_all().await;
+        self.sink.disconnected().await;
+    }
+
+    async fn get_screen_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    async fn get_cursor_position(&self) -> (u16, u16) {
+        (self.x, self.y)
+    }
+
+    async fn set_cursor_position(&mut self, x: u16, y: u16) {
+        let (dx, dy) = (x as i32 - self.x as i32, y as i32 - self.y as i32);
+        self.x = x;
+        self.y = y;
+        if self.hid().relative_pointer() {
+            self.move_by(dx, dy).await;
+        } else {
+            let (phy_x, phy_y) = self.scale_position(x, y);
+            let mut report = [0; MAX_REPORT_SIZE];
+            let ret = self.hid.set_cursor_position(phy_x, phy_y, &mut report);
+            self.sink.send_report(ret).await;
+        }
+    }
+
+    async fn move_cursor(&mut self, x: i16, y: i16) {
+        if self.hid().relative_pointer() {
+            // The host moves its own cursor and stops it at the edges, this is only a guess of
+            // where it is for the next absolute move
+            self.x = (self.x as i32 + x as i32).clamp(0, self.width as i32 - 1) as u16;
+            self.y = (self.y as i32 + y as i32).clamp(0, self.height as i32 - 1) as u16;
+            self.move_by(x as i32, y as i32).await;
+        } else {
+            let (cx, cy) = (self.x as i32 + x as i32, self.y as i32 + y as i32);
+            self.set_cursor_position(cx as u16, cy as u16).await;
+        }
+    }
+
+    async fn mouse_down(&mut self, button: i8) {
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().mouse_down(button, &mut report);
+        self.sink.send_report(ret).await;
+    }
+
+    async fn mouse_up(&mut self, button: i8) {
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().mouse_up(button, &mut report);
+        self.sink.send_report(ret).await;
+    }
+
+    async fn mouse_wheel(&mut self, x: i16, y: i16) {
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().mouse_scroll(x, y, &mut report);
+        self.sink.send_report(ret).await;
+    }
+
+    async fn key_down(&mut self, key: u16, mask: u16, button: u16) {
+        if self.sink.take_key(key, mask, button).await {
+            self.taken_button = Some(button);
+            return;
+        }
+        if self.is_half_duplex(key) {
+            self.tap_key(key, mask, button).await;
+            return;
+        }
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().key_down(key, mask, button, &mut report);
+        self.sink.send_report(ret).await;
+        // Characters typed with Unicode input take a few more
+        while let Some(ret) = self.hid.next_report(&mut report) {
+            self.sink.send_report(ret).await;
+        }
+    }
+
+    async fn key_repeat(&mut self, key: u16, mask: u16, button: u16, count: u16) {
+        self.sink.key_repeat(key, mask, button, count).await;
+    }
+
+    async fn key_up(&mut self, key: u16, mask: u16, button: u16) {
+        if self.taken_button == Some(button) {
+            self.taken_button = None;
+            return;
+        }
+        if self.is_half_duplex(key) {
+            self.tap_key(key, mask, button).await;
+            return;
+        }
+        let mut report = [0; MAX_REPORT_SIZE];
+        let ret = self.hid().key_up(key, mask, button, &mut report);
+        self.sink.send_report(ret).await;
+    }
+
+    async fn set_clipboard(&mut self, id: u8, text: &str) {
+        self.sink.set_clipboard(id, text).await;
+    }
+
+    async fn set_options(&mut self, options: Options) {
+        self.options = options;
+        self.sink.set_options(options).await;
+    }
+
+    async fn reset_options(&mut self) {
+        self.options = Options::default();
+        self.sink.reset_options().await;
+    }
+
+    async fn enter(&mut self, x: u16, y: u16, mask: u16) {
+        self.set_cursor_position(x, y).await;
+        // Caps lock and friends stay the way they were on the server's keyboard, num lock and
+        // scroll lock only once the server has shown it tracks them
+        let mut report = [0; MAX_REPORT_SIZE];
+        let leds = self.sink.keyboard_leds();
+        if let Some(ret) = self.hid().lock_keys_down(mask, leds, &mut report) {
+            self.sink.send_report(ret).await;
+            let ret = self.hid().lock_keys_up(&mut report);
+            self.sink.send_report(ret).await;
+        }
+        self.sink.enter(x, y, mask).await;
+    }
+
+    async fn leave(&mut self) {
+        self.release_all().await;
+        self.sink.leave().await;
+    }
+}
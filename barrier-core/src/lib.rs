@@ -0,0 +1,10 @@
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+#![allow(async_fn_in_trait)]
+
+// This must go first so the logging macros are visible to the other modules
+mod fmt;
+
+pub mod barrier;
+pub mod hid_actuator;
+pub mod synergy_hid;
+pub mod tls;
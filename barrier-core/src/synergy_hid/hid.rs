@@ -0,0 +1,279 @@
+#[derive(Debug, Default)]
+pub struct AbsMouseReport {
+    button: u8,
+    x: u16,
+    y: u16,
+}
+
+impl AbsMouseReport {
+    pub fn move_to(&mut self, x: u16, y: u16) -> [u8; 7] {
+        self.x = x;
+        self.y = y;
+        self.send(None, None)
+    }
+
+    pub fn mouse_down(&mut self, button: u8) -> [u8; 7] {
+        self.button |= button;
+        self.send(None, None)
+    }
+
+    pub fn mouse_up(&mut self, button: u8) -> [u8; 7] {
+        self.button &= !button;
+        self.send(None, None)
+    }
+
+    pub fn mouse_wheel(&mut self, scroll: i8, pan: i8) -> [u8; 7] {
+        self.send(scroll, pan)
+    }
+
+    pub fn clear(&mut self) -> [u8; 7] {
+        self.button = 0;
+        self.send(None, None)
+    }
+
+    fn send<S: Into<Option<i8>>, P: Into<Option<i8>>>(&self, scroll: S, pan: P) -> [u8; 7] {
+        let scroll = scroll.into().unwrap_or(0);
+        let pan = pan.into().unwrap_or(0);
+        let mut report = [0u8; 7];
+        report[0] = self.button;
+        report[1] = (self.x & 0xff) as u8;
+        report[2] = (self.x >> 8) as u8;
+        report[3] = (self.y & 0xff) as u8;
+        report[4] = (self.y >> 8) as u8;
+        report[5] = scroll as u8;
+        report[6] = pan as u8;
+        report
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RelMouseReport {
+    button: u8,
+}
+
+impl RelMouseReport {
+    pub fn move_by(&mut self, x: i8, y: i8) -> [u8; 5] {
+        self.send(x, y, 0, 0)
+    }
+
+    pub fn mouse_down(&mut self, button: u8) -> [u8; 5] {
+        self.button |= button;
+        self.send(0, 0, 0, 0)
+    }
+
+    pub fn mouse_up(&mut self, button: u8) -> [u8; 5] {
+        self.button &= !button;
+        self.send(0, 0, 0, 0)
+    }
+
+    pub fn mouse_wheel(&mut self, scroll: i8, pan: i8) -> [u8; 5] {
+        self.send(0, 0, scroll, pan)
+    }
+
+    pub fn clear(&mut self) -> [u8; 5] {
+        self.button = 0;
+        self.send(0, 0, 0, 0)
+    }
+
+    fn send(&self, x: i8, y: i8, scroll: i8, pan: i8) -> [u8; 5] {
+        [self.button, x as u8, y as u8, scroll as u8, pan as u8]
+    }
+}
+
+// Modifier byte and one bit for each of the usages 0x00 to 0xDF
+pub const NKRO_REPORT_SIZE: usize = 29;
+
+const HID_KEY_ERROR_ROLL_OVER: u8 = 0x01;
+
+#[derive(Clone, Debug, Default)]
+pub struct KeyboardReport {
+    modifier: u8,
+    // Modifiers the last key needs on top of the modifier keys that are down, and the ones it
+    // needs up
+    extra_modifier: u8,
+    suppressed_modifier: u8,
+    // Keys down in the order they were pressed, as long as they fit in the boot report
+    keycode: [u8; 6],
+    // Every key down, one bit per usage
+    keys: [u8; NKRO_REPORT_SIZE - 1],
+}
+
+impl KeyboardReport {
+    pub fn press(&mut self, key: u8) -> [u8; 8] {
+        match self.get_modifier(key) {
+            Some(modifier) => self.modifier |= modifier,
+            // Usages past the bitmap aren't keys
+            None if key == 0 || key as usize / 8 >= self.keys.len() => {}
+            None if self.is_down(key) => {}
+            None => {
+                self.keys[key as usize / 8] |= 1 << (key % 8);
+                // Past six keys it's only in the bitmap, the boot report shows the rollover
+                if let Some(slot) = self.keycode.iter_mut().find(|k| **k == 0) {
+                    *slot = key;
+                }
+            }
+        }
+        self.send()
+    }
+
+    pub fn release(&mut self, key: u8) -> [u8; 8] {
+        match self.get_modifier(key) {
+            Some(modifier) => self.modifier &= !modifier,
+            None if key == 0 || key as usize / 8 >= self.keys.len() => {}
+            None => {
+                self.keys[key as usize / 8] &= !(1 << (key % 8));
+                for i in 0..6 {
+                    if self.keycode[i] == key {
+                        self.keycode[i] = 0;
+                        break;
+                    }
+                }
+                // Compact the keycode array
+                let mut pos = 0;
+                for i in 0..6 {
+                    if self.keycode[i] != 0 {
+                        self.keycode.swap(i, pos);
+                        pos += 1;
+                    }
+                }
+                // Keys pressed during a rollover take the free places once they all fit again
+                if self.key_count() <= 6 {
+                    for k in 1..=(self.keys.len() * 8 - 1) as u8 {
+                        if pos < 6 && self.is_down(k) && !self.keycode[..pos].contains(&k) {
+                            self.keycode[pos] = k;
+                            pos += 1;
+                        }
+                    }
+                }
+            }
+        }
+        self.send()
+    }
+
+    // Modifier keys that are down, without the override
+    pub fn modifier(&self) -> u8 {
+        self.modifier
+    }
+
+    // Stops hiding a modifier whose key is pressed while the override is on
+    pub fn show_modifier(&mut self, modifier: u8) -> [u8; 8] {
+        self.suppressed_modifier &= !modifier;
+        self.send()
+    }
+
+    pub fn set_modifier_override(&mut self, extra: u8, suppressed: u8) -> [u8; 8] {
+        self.extra_modifier = extra;
+        self.suppressed_modifier = suppressed;
+        self.send()
+    }
+
+    pub fn clear(&mut self) -> [u8; 8] {
+        self.modifier = 0;
+        self.extra_modifier = 0;
+        self.suppressed_modifier = 0;
+        self.keycode = [0; 6];
+        self.keys = [0; NKRO_REPORT_SIZE - 1];
+        self.send()
+    }
+
+    // Same keys as the boot report, without the six key limit
+    pub fn nkro(&self) -> [u8; NKRO_REPORT_SIZE] {
+        let mut report = [0u8; NKRO_REPORT_SIZE];
+        report[0] = self.modifier_byte();
+        report[1..].copy_from_slice(&self.keys);
+        report
+    }
+
+    // Boot report, with ErrorRollOver in every key slot while more than six keys are down
+    pub fn send(&self) -> [u8; 8] {
+        let mut report = [0u8; 8];
+        report[0] = self.modifier_byte();
+        report[1] = 0;
+        if self.key_count() > 6 {
+            report[2..].fill(HID_KEY_ERROR_ROLL_OVER);
+        } else {
+            report[2..(6 + 2)].copy_from_slice(&self.keycode);
+        }
+        report
+    }
+
+    fn modifier_byte(&self) -> u8 {
+        (self.modifier & !self.suppressed_modifier) | self.extra_modifier
+    }
+
+    fn is_down(&self, key: u8) -> bool {
+        self.keys[key as usize / 8] & (1 << (key % 8)) != 0
+    }
+
+    fn key_count(&self) -> u32 {
+        self.keys.iter().map(|b| b.count_ones()).sum()
+    }
+
+    fn get_modifier(&self, key: u8) -> Option<u8> {
+        match key {
+            0xE0 => Some(0x01), // Left Control
+            0xE1 => Some(0x02), // Left Shift
+            0xE2 => Some(0x04), // Left Alt
+            0xE3 => Some(0x08), // Left GUI
+            0xE4 => Some(0x10), // Right Control
+            0xE5 => Some(0x20), // Right Shift
+            0xE6 => Some(0x40), // Right Alt
+            0xE7 => Some(0x80), // Right GUI
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConsumerReport {
+    code: u16,
+}
+
+impl ConsumerReport {
+    pub fn press(&mut self, code: u16) -> [u8; 2] {
+        self.code = code;
+        self.send()
+    }
+
+    pub fn release(&mut self) -> [u8; 2] {
+        self.code = 0;
+        self.send()
+    }
+
+    pub fn clear(&mut self) -> [u8; 2] {
+        self.code = 0;
+        self.send()
+    }
+
+    fn send(&self) -> [u8; 2] {
+        let mut report = [0u8; 2];
+        report[0] = (self.code & 0xff) as u8;
+        report[1] = (self.code >> 8) as u8;
+        report
+    }
+}
+
+/**
+ * Keyboard LEDs as set by the host with the keyboard's output report, they follow the lock keys.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyboardLeds {
+    pub num_lock: bool,
+    pub caps_lock: bool,
+    pub scroll_lock: bool,
+    pub compose: bool,
+    pub kana: bool,
+}
+
+impl KeyboardLeds {
+    pub fn from_report(report: u8) -> Self {
+        Self {
+            num_lock: report & 0x01 != 0,
+            caps_lock: report & 0x02 != 0,
+            scroll_lock: report & 0x04 != 0,
+            compose: report & 0x08 != 0,
+            kana: report & 0x10 != 0,
+        }
+    }
+}
@@ -0,0 +1,797 @@
+use thiserror::Error;
+
+use super::keycodes::*;
+
+// Modifier bits of the keyboard report a character can need on top of its key
+pub const MODIFIER_BIT_CONTROL: u8 = 0x01;
+pub const MODIFIER_BIT_SHIFT: u8 = 0x02;
+pub const MODIFIER_BIT_ALT: u8 = 0x04;
+pub const MODIFIER_BIT_GUI: u8 = 0x08;
+pub const MODIFIER_BIT_ALT_GR: u8 = 0x40;
+
+const LEVEL_MODIFIERS: [u8; 3] = [0, MODIFIER_BIT_SHIFT, MODIFIER_BIT_ALT_GR];
+
+// No character on this level
+const NONE: char = '\0';
+
+/**
+ * A key of the layout and what it types alone, with Shift and with AltGr. Dead keys are left out,
+ * they wait for the next key instead of typing their character.
+ */
+type LayoutKey = (u8, [char; 3]);
+
+/**
+ * Keyboard layout the host is set to. The server sends characters, the key that types one depends
+ * on the layout the host uses to read the keyboard.
+ */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Layout {
+    #[default]
+    Us,
+    De,
+    Fr,
+    Uk,
+    // Swedish and Finnish
+    Nordic,
+    Jis,
+    // US Dvorak
+    Dvorak,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("unknown keyboard layout")]
+pub struct UnknownLayout;
+
+impl Layout {
+    pub const ALL: [Layout; 7] = [
+        Layout::Us,
+        Layout::De,
+        Layout::Fr,
+        Layout::Uk,
+        Layout::Nordic,
+        Layout::Jis,
+        Layout::Dvorak,
+    ];
+
+    // Name used by `from_str`, the `KEYBOARD_LAYOUT` build option of the firmware
+    pub const fn name(self) -> &'static str {
+        match self {
+            Layout::Us => "us",
+            Layout::De => "de",
+            Layout::Fr => "fr",
+            Layout::Uk => "uk",
+            Layout::Nordic => "nordic",
+            Layout::Jis => "jis",
+            Layout::Dvorak => "dvorak",
+        }
+    }
+
+    /**
+     * Key and modifier bits that type the character, None if no single key of the layout types it.
+     */
+    pub fn lookup(self, c: char) -> Option<(u8, u8)> {
+        if c == NONE {
+            return None;
+        }
+        self.keys().iter().find_map(|(key, chars)| {
+            let level = chars.iter().position(|&k| k == c)?;
+            Some((*key, LEVEL_MODIFIERS[level]))
+        })
+    }
+
+    const fn keys(self) -> &'static [LayoutKey] {
+        match self {
+            Layout::Us => US,
+            Layout::De => DE,
+            Layout::Fr => FR,
+            Layout::Uk => UK,
+            Layout::Nordic => NORDIC,
+            Layout::Jis => JIS,
+            Layout::Dvorak => DVORAK,
+        }
+    }
+}
+
+impl core::str::FromStr for Layout {
+    type Err = UnknownLayout;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Layout::ALL
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(s))
+            .ok_or(UnknownLayout)
+    }
+}
+
+// The key left of 1 and the ones around Enter change places between ANSI, ISO and JIS keyboards,
+// ISO layouts use the Non-US # and Non-US \ usages for the keys ANSI doesn't have
+
+const US: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, ['`', '~', NONE]),
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '@', NONE]),
+    (HID_KEY_3, ['3', '#', NONE]),
+    (HID_KEY_4, ['4', '$', NONE]),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '^', NONE]),
+    (HID_KEY_7, ['7', '&', NONE]),
+    (HID_KEY_8, ['8', '*', NONE]),
+    (HID_KEY_9, ['9', '(', NONE]),
+    (HID_KEY_0, ['0', ')', NONE]),
+    (HID_KEY_MINUS, ['-', '_', NONE]),
+    (HID_KEY_EQUAL, ['=', '+', NONE]),
+    (HID_KEY_Q, ['q', 'Q', NONE]),
+    (HID_KEY_W, ['w', 'W', NONE]),
+    (HID_KEY_E, ['e', 'E', NONE]),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['y', 'Y', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['[', '{', NONE]),
+    (HID_KEY_BRACKET_RIGHT, [']', '}', NONE]),
+    (HID_KEY_BACKSLASH, ['\\', '|', NONE]),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, [';', ':', NONE]),
+    (HID_KEY_APOSTROPHE, ['\'', '"', NONE]),
+    (HID_KEY_Z, ['z', 'Z', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, ['m', 'M', NONE]),
+    (HID_KEY_COMMA, [',', '<', NONE]),
+    (HID_KEY_PERIOD, ['.', '>', NONE]),
+    (HID_KEY_SLASH, ['/', '?', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+// ^, ´ and ` are dead keys
+const DE: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, [NONE, '°', NONE]),
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '"', '²']),
+    (HID_KEY_3, ['3', '§', '³']),
+    (HID_KEY_4, ['4', '$', NONE]),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '&', NONE]),
+    (HID_KEY_7, ['7', '/', '{']),
+    (HID_KEY_8, ['8', '(', '[']),
+    (HID_KEY_9, ['9', ')', ']']),
+    (HID_KEY_0, ['0', '=', '}']),
+    (HID_KEY_MINUS, ['ß', '?', '\\']),
+    (HID_KEY_Q, ['q', 'Q', '@']),
+    (HID_KEY_W, ['w', 'W', NONE]),
+    (HID_KEY_E, ['e', 'E', '€']),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['z', 'Z', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['ü', 'Ü', NONE]),
+    (HID_KEY_BRACKET_RIGHT, ['+', '*', '~']),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, ['ö', 'Ö', NONE]),
+    (HID_KEY_APOSTROPHE, ['ä', 'Ä', NONE]),
+    (HID_KEY_EUROPE_1, ['#', '\'', NONE]),
+    (HID_KEY_EUROPE_2, ['<', '>', '|']),
+    (HID_KEY_Z, ['y', 'Y', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, ['m', 'M', 'µ']),
+    (HID_KEY_COMMA, [',', ';', NONE]),
+    (HID_KEY_PERIOD, ['.', ':', NONE]),
+    (HID_KEY_SLASH, ['-', '_', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+// AZERTY as on Windows, ^, ¨, ~ and ` are dead keys
+const FR: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, ['²', NONE, NONE]),
+    (HID_KEY_1, ['&', '1', NONE]),
+    (HID_KEY_2, ['é', '2', NONE]),
+    (HID_KEY_3, ['"', '3', '#']),
+    (HID_KEY_4, ['\'', '4', '{']),
+    (HID_KEY_5, ['(', '5', '[']),
+    (HID_KEY_6, ['-', '6', '|']),
+    (HID_KEY_7, ['è', '7', NONE]),
+    (HID_KEY_8, ['_', '8', '\\']),
+    (HID_KEY_9, ['ç', '9', '^']),
+    (HID_KEY_0, ['à', '0', '@']),
+    (HID_KEY_MINUS, [')', '°', ']']),
+    (HID_KEY_EQUAL, ['=', '+', '}']),
+    (HID_KEY_Q, ['a', 'A', NONE]),
+    (HID_KEY_W, ['z', 'Z', NONE]),
+    (HID_KEY_E, ['e', 'E', '€']),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['y', 'Y', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_RIGHT, ['$', '£', '¤']),
+    (HID_KEY_A, ['q', 'Q', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, ['m', 'M', NONE]),
+    (HID_KEY_APOSTROPHE, ['ù', '%', NONE]),
+    (HID_KEY_EUROPE_1, ['*', 'µ', NONE]),
+    (HID_KEY_EUROPE_2, ['<', '>', NONE]),
+    (HID_KEY_Z, ['w', 'W', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, [',', '?', NONE]),
+    (HID_KEY_COMMA, [';', '.', NONE]),
+    (HID_KEY_PERIOD, [':', '/', NONE]),
+    (HID_KEY_SLASH, ['!', '§', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+const UK: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, ['`', '¬', NONE]),
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '"', NONE]),
+    (HID_KEY_3, ['3', '£', NONE]),
+    (HID_KEY_4, ['4', '$', '€']),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '^', NONE]),
+    (HID_KEY_7, ['7', '&', NONE]),
+    (HID_KEY_8, ['8', '*', NONE]),
+    (HID_KEY_9, ['9', '(', NONE]),
+    (HID_KEY_0, ['0', ')', NONE]),
+    (HID_KEY_MINUS, ['-', '_', NONE]),
+    (HID_KEY_EQUAL, ['=', '+', NONE]),
+    (HID_KEY_Q, ['q', 'Q', NONE]),
+    (HID_KEY_W, ['w', 'W', NONE]),
+    (HID_KEY_E, ['e', 'E', NONE]),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['y', 'Y', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['[', '{', NONE]),
+    (HID_KEY_BRACKET_RIGHT, [']', '}', NONE]),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, [';', ':', NONE]),
+    (HID_KEY_APOSTROPHE, ['\'', '@', NONE]),
+    (HID_KEY_EUROPE_1, ['#', '~', NONE]),
+    (HID_KEY_EUROPE_2, ['\\', '|', NONE]),
+    (HID_KEY_Z, ['z', 'Z', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, ['m', 'M', NONE]),
+    (HID_KEY_COMMA, [',', '<', NONE]),
+    (HID_KEY_PERIOD, ['.', '>', NONE]),
+    (HID_KEY_SLASH, ['/', '?', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+// ´, `, ¨, ^ and ~ are dead keys
+const NORDIC: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, ['§', '½', NONE]),
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '"', '@']),
+    (HID_KEY_3, ['3', '#', '£']),
+    (HID_KEY_4, ['4', '¤', '$']),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '&', NONE]),
+    (HID_KEY_7, ['7', '/', '{']),
+    (HID_KEY_8, ['8', '(', '[']),
+    (HID_KEY_9, ['9', ')', ']']),
+    (HID_KEY_0, ['0', '=', '}']),
+    (HID_KEY_MINUS, ['+', '?', '\\']),
+    (HID_KEY_Q, ['q', 'Q', NONE]),
+    (HID_KEY_W, ['w', 'W', NONE]),
+    (HID_KEY_E, ['e', 'E', '€']),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['y', 'Y', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['å', 'Å', NONE]),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, ['ö', 'Ö', NONE]),
+    (HID_KEY_APOSTROPHE, ['ä', 'Ä', NONE]),
+    (HID_KEY_EUROPE_1, ['\'', '*', NONE]),
+    (HID_KEY_EUROPE_2, ['<', '>', '|']),
+    (HID_KEY_Z, ['z', 'Z', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, ['m', 'M', 'µ']),
+    (HID_KEY_COMMA, [',', ';', NONE]),
+    (HID_KEY_PERIOD, ['.', ':', NONE]),
+    (HID_KEY_SLASH, ['-', '_', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+// The key left of 1 is Hankaku/Zenkaku. International 1 is the Ro key next to right Shift and
+// International 3 the Yen key next to Backspace.
+const JIS: &[LayoutKey] = &[
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '"', NONE]),
+    (HID_KEY_3, ['3', '#', NONE]),
+    (HID_KEY_4, ['4', '$', NONE]),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '&', NONE]),
+    (HID_KEY_7, ['7', '\'', NONE]),
+    (HID_KEY_8, ['8', '(', NONE]),
+    (HID_KEY_9, ['9', ')', NONE]),
+    (HID_KEY_0, ['0', NONE, NONE]),
+    (HID_KEY_MINUS, ['-', '=', NONE]),
+    (HID_KEY_EQUAL, ['^', '~', NONE]),
+    (HID_KEY_KANJI3, ['¥', '|', NONE]),
+    (HID_KEY_Q, ['q', 'Q', NONE]),
+    (HID_KEY_W, ['w', 'W', NONE]),
+    (HID_KEY_E, ['e', 'E', NONE]),
+    (HID_KEY_R, ['r', 'R', NONE]),
+    (HID_KEY_T, ['t', 'T', NONE]),
+    (HID_KEY_Y, ['y', 'Y', NONE]),
+    (HID_KEY_U, ['u', 'U', NONE]),
+    (HID_KEY_I, ['i', 'I', NONE]),
+    (HID_KEY_O, ['o', 'O', NONE]),
+    (HID_KEY_P, ['p', 'P', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['@', '`', NONE]),
+    (HID_KEY_BRACKET_RIGHT, ['[', '{', NONE]),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['s', 'S', NONE]),
+    (HID_KEY_D, ['d', 'D', NONE]),
+    (HID_KEY_F, ['f', 'F', NONE]),
+    (HID_KEY_G, ['g', 'G', NONE]),
+    (HID_KEY_H, ['h', 'H', NONE]),
+    (HID_KEY_J, ['j', 'J', NONE]),
+    (HID_KEY_K, ['k', 'K', NONE]),
+    (HID_KEY_L, ['l', 'L', NONE]),
+    (HID_KEY_SEMICOLON, [';', '+', NONE]),
+    (HID_KEY_APOSTROPHE, [':', '*', NONE]),
+    (HID_KEY_EUROPE_1, [']', '}', NONE]),
+    (HID_KEY_Z, ['z', 'Z', NONE]),
+    (HID_KEY_X, ['x', 'X', NONE]),
+    (HID_KEY_C, ['c', 'C', NONE]),
+    (HID_KEY_V, ['v', 'V', NONE]),
+    (HID_KEY_B, ['b', 'B', NONE]),
+    (HID_KEY_N, ['n', 'N', NONE]),
+    (HID_KEY_M, ['m', 'M', NONE]),
+    (HID_KEY_COMMA, [',', '<', NONE]),
+    (HID_KEY_PERIOD, ['.', '>', NONE]),
+    (HID_KEY_SLASH, ['/', '?', NONE]),
+    (HID_KEY_KANJI1, ['\\', '_', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+// Keys are named after their US QWERTY place
+const DVORAK: &[LayoutKey] = &[
+    (HID_KEY_GRAVE, ['`', '~', NONE]),
+    (HID_KEY_1, ['1', '!', NONE]),
+    (HID_KEY_2, ['2', '@', NONE]),
+    (HID_KEY_3, ['3', '#', NONE]),
+    (HID_KEY_4, ['4', '$', NONE]),
+    (HID_KEY_5, ['5', '%', NONE]),
+    (HID_KEY_6, ['6', '^', NONE]),
+    (HID_KEY_7, ['7', '&', NONE]),
+    (HID_KEY_8, ['8', '*', NONE]),
+    (HID_KEY_9, ['9', '(', NONE]),
+    (HID_KEY_0, ['0', ')', NONE]),
+    (HID_KEY_MINUS, ['[', '{', NONE]),
+    (HID_KEY_EQUAL, [']', '}', NONE]),
+    (HID_KEY_Q, ['\'', '"', NONE]),
+    (HID_KEY_W, [',', '<', NONE]),
+    (HID_KEY_E, ['.', '>', NONE]),
+    (HID_KEY_R, ['p', 'P', NONE]),
+    (HID_KEY_T, ['y', 'Y', NONE]),
+    (HID_KEY_Y, ['f', 'F', NONE]),
+    (HID_KEY_U, ['g', 'G', NONE]),
+    (HID_KEY_I, ['c', 'C', NONE]),
+    (HID_KEY_O, ['r', 'R', NONE]),
+    (HID_KEY_P, ['l', 'L', NONE]),
+    (HID_KEY_BRACKET_LEFT, ['/', '?', NONE]),
+    (HID_KEY_BRACKET_RIGHT, ['=', '+', NONE]),
+    (HID_KEY_BACKSLASH, ['\\', '|', NONE]),
+    (HID_KEY_A, ['a', 'A', NONE]),
+    (HID_KEY_S, ['o', 'O', NONE]),
+    (HID_KEY_D, ['e', 'E', NONE]),
+    (HID_KEY_F, ['u', 'U', NONE]),
+    (HID_KEY_G, ['i', 'I', NONE]),
+    (HID_KEY_H, ['d', 'D', NONE]),
+    (HID_KEY_J, ['h', 'H', NONE]),
+    (HID_KEY_K, ['t', 'T', NONE]),
+    (HID_KEY_L, ['n', 'N', NONE]),
+    (HID_KEY_SEMICOLON, ['s', 'S', NONE]),
+    (HID_KEY_APOSTROPHE, ['-', '_', NONE]),
+    (HID_KEY_Z, [';', ':', NONE]),
+    (HID_KEY_X, ['q', 'Q', NONE]),
+    (HID_KEY_C, ['j', 'J', NONE]),
+    (HID_KEY_V, ['k', 'K', NONE]),
+    (HID_KEY_B, ['x', 'X', NONE]),
+    (HID_KEY_N, ['b', 'B', NONE]),
+    (HID_KEY_M, ['m', 'M', NONE]),
+    (HID_KEY_COMMA, ['w', 'W', NONE]),
+    (HID_KEY_PERIOD, ['v', 'V', NONE]),
+    (HID_KEY_SLASH, ['z', 'Z', NONE]),
+    (HID_KEY_SPACE, [' ', NONE, NONE]),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Keys of a row and what they type on each level, spaces where they type nothing
+    type Row = (Vec<u8>, [&'static str; 3]);
+
+    const DIGITS: [u8; 10] = [
+        HID_KEY_1, HID_KEY_2, HID_KEY_3, HID_KEY_4, HID_KEY_5, HID_KEY_6, HID_KEY_7, HID_KEY_8,
+        HID_KEY_9, HID_KEY_0,
+    ];
+    const TOP: [u8; 10] = [
+        HID_KEY_Q, HID_KEY_W, HID_KEY_E, HID_KEY_R, HID_KEY_T, HID_KEY_Y, HID_KEY_U, HID_KEY_I,
+        HID_KEY_O, HID_KEY_P,
+    ];
+    const HOME: [u8; 9] = [
+        HID_KEY_A, HID_KEY_S, HID_KEY_D, HID_KEY_F, HID_KEY_G, HID_KEY_H, HID_KEY_J, HID_KEY_K,
+        HID_KEY_L,
+    ];
+    const BOTTOM: [u8; 7] = [
+        HID_KEY_Z, HID_KEY_X, HID_KEY_C, HID_KEY_V, HID_KEY_B, HID_KEY_N, HID_KEY_M,
+    ];
+
+    fn row(before: &[u8], keys: &[u8], after: &[u8], levels: [&'static str; 3]) -> Row {
+        ([before, keys, after].concat(), levels)
+    }
+
+    // Every character of the rows is typed by its key, and the layout types nothing else
+    fn check(layout: Layout, rows: &[Row]) {
+        let mut count = 0;
+        for (keys, levels) in rows {
+            for (level, chars) in levels.iter().enumerate() {
+                assert_eq!(chars.chars().count(), keys.len(), "{:?} {}", layout, chars);
+                for (&key, c) in keys.iter().zip(chars.chars()).filter(|(_, c)| *c != ' ') {
+                    assert_eq!(
+                        layout.lookup(c),
+                        Some((key, LEVEL_MODIFIERS[level])),
+                        "{:?} {:?}",
+                        layout,
+                        c
+                    );
+                    count += 1;
+                }
+            }
+        }
+        assert_eq!(layout.lookup(' '), Some((HID_KEY_SPACE, 0)));
+        let chars = layout.keys().iter().flat_map(|(_, c)| c);
+        assert_eq!(chars.filter(|&&c| c != NONE).count(), count + 1);
+    }
+
+    #[test]
+    fn test_us() {
+        check(
+            Layout::Us,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS, HID_KEY_EQUAL],
+                    ["`1234567890-=", "~!@#$%^&*()_+", "             "],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[
+                        HID_KEY_BRACKET_LEFT,
+                        HID_KEY_BRACKET_RIGHT,
+                        HID_KEY_BACKSLASH,
+                    ],
+                    ["qwertyuiop[]\\", "QWERTYUIOP{}|", "             "],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE],
+                    ["asdfghjkl;'", "ASDFGHJKL:\"", "           "],
+                ),
+                row(
+                    &[],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    ["zxcvbnm,./", "ZXCVBNM<>?", "          "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_de() {
+        check(
+            Layout::De,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS],
+                    [" 1234567890ß", "°!\"§$%&/()=?", "  ²³   {[]}\\"],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[HID_KEY_BRACKET_LEFT, HID_KEY_BRACKET_RIGHT],
+                    ["qwertzuiopü+", "QWERTZUIOPÜ*", "@ €        ~"],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE, HID_KEY_EUROPE_1],
+                    ["asdfghjklöä#", "ASDFGHJKLÖÄ'", "            "],
+                ),
+                row(
+                    &[HID_KEY_EUROPE_2],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    ["<yxcvbnm,.-", ">YXCVBNM;:_", "|      µ   "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_fr() {
+        check(
+            Layout::Fr,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS, HID_KEY_EQUAL],
+                    ["²&é\"'(-è_çà)=", " 1234567890°+", "   #{[| \\^@]}"],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[HID_KEY_BRACKET_RIGHT],
+                    ["azertyuiop$", "AZERTYUIOP£", "  €       ¤"],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE, HID_KEY_EUROPE_1],
+                    ["qsdfghjklmù*", "QSDFGHJKLM%µ", "            "],
+                ),
+                row(
+                    &[HID_KEY_EUROPE_2],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    ["<wxcvbn,;:!", ">WXCVBN?./§", "           "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_uk() {
+        check(
+            Layout::Uk,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS, HID_KEY_EQUAL],
+                    ["`1234567890-=", "¬!\"£$%^&*()_+", "    €        "],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[HID_KEY_BRACKET_LEFT, HID_KEY_BRACKET_RIGHT],
+                    ["qwertyuiop[]", "QWERTYUIOP{}", "            "],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE, HID_KEY_EUROPE_1],
+                    ["asdfghjkl;'#", "ASDFGHJKL:@~", "            "],
+                ),
+                row(
+                    &[HID_KEY_EUROPE_2],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    ["\\zxcvbnm,./", "|ZXCVBNM<>?", "           "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_nordic() {
+        check(
+            Layout::Nordic,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS],
+                    ["§1234567890+", "½!\"#¤%&/()=?", "  @£$  {[]}\\"],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[HID_KEY_BRACKET_LEFT],
+                    ["qwertyuiopå", "QWERTYUIOPÅ", "  €        "],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE, HID_KEY_EUROPE_1],
+                    ["asdfghjklöä'", "ASDFGHJKLÖÄ*", "            "],
+                ),
+                row(
+                    &[HID_KEY_EUROPE_2],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    ["<zxcvbnm,.-", ">ZXCVBNM;:_", "|      µ   "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_jis() {
+        check(
+            Layout::Jis,
+            &[
+                row(
+                    &[],
+                    &DIGITS,
+                    &[HID_KEY_MINUS, HID_KEY_EQUAL, HID_KEY_KANJI3],
+                    ["1234567890-^¥", "!\"#$%&'() =~|", "             "],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[HID_KEY_BRACKET_LEFT, HID_KEY_BRACKET_RIGHT],
+                    ["qwertyuiop@[", "QWERTYUIOP`{", "            "],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE, HID_KEY_EUROPE_1],
+                    ["asdfghjkl;:]", "ASDFGHJKL+*}", "            "],
+                ),
+                row(
+                    &[],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH, HID_KEY_KANJI1],
+                    ["zxcvbnm,./\\", "ZXCVBNM<>?_", "           "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_dvorak() {
+        check(
+            Layout::Dvorak,
+            &[
+                row(
+                    &[HID_KEY_GRAVE],
+                    &DIGITS,
+                    &[HID_KEY_MINUS, HID_KEY_EQUAL],
+                    ["`1234567890[]", "~!@#$%^&*(){}", "             "],
+                ),
+                row(
+                    &[],
+                    &TOP,
+                    &[
+                        HID_KEY_BRACKET_LEFT,
+                        HID_KEY_BRACKET_RIGHT,
+                        HID_KEY_BACKSLASH,
+                    ],
+                    ["',.pyfgcrl/=\\", "\"<>PYFGCRL?+|", "             "],
+                ),
+                row(
+                    &[],
+                    &HOME,
+                    &[HID_KEY_SEMICOLON, HID_KEY_APOSTROPHE],
+                    ["aoeuidhtns-", "AOEUIDHTNS_", "           "],
+                ),
+                row(
+                    &[],
+                    &BOTTOM,
+                    &[HID_KEY_COMMA, HID_KEY_PERIOD, HID_KEY_SLASH],
+                    [";qjkxbmwvz", ":QJKXBMWVZ", "          "],
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ascii() {
+        // Only typed with dead keys
+        let missing = [
+            (Layout::Us, ""),
+            (Layout::De, "^`"),
+            (Layout::Fr, "`~"),
+            (Layout::Uk, ""),
+            (Layout::Nordic, "^`~"),
+            (Layout::Jis, ""),
+            (Layout::Dvorak, ""),
+        ];
+        for (layout, missing) in missing {
+            for c in ' '..='~' {
+                assert_eq!(
+                    layout.lookup(c).is_none(),
+                    missing.contains(c),
+                    "{:?} {:?}",
+                    layout,
+                    c
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        for layout in Layout::ALL {
+            assert_eq!(layout.name().parse(), Ok(layout));
+        }
+        assert_eq!("DE".parse(), Ok(Layout::De));
+        assert_eq!("azerty".parse::<Layout>(), Err(UnknownLayout));
+    }
+}
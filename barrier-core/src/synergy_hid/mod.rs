@@ -0,0 +1,1342 @@
+mod descriptors;
+mod hid;
+mod keycodes;
+mod layouts;
+mod scancodes;
+mod unicode;
+
+pub use hid::KeyboardLeds;
+pub(super) use hid::*;
+pub(crate) use keycodes::{
+    synergy_mouse_button, synergy_to_hid, KeyCode, HID_KEY_CAPS_LOCK, HID_KEY_CONTROL_LEFT,
+    HID_KEY_ENTER, HID_KEY_GUI_RIGHT, HID_KEY_NUM_LOCK, HID_KEY_SCROLL_LOCK, HID_KEY_TAB,
+};
+pub use layouts::{Layout, UnknownLayout};
+use layouts::{
+    MODIFIER_BIT_ALT, MODIFIER_BIT_ALT_GR, MODIFIER_BIT_CONTROL, MODIFIER_BIT_GUI,
+    MODIFIER_BIT_SHIFT,
+};
+pub use scancodes::{ScanCodes, UnknownScanCodes};
+pub use unicode::{UnicodeInput, UnicodeSteps, UnknownUnicodeInput};
+
+pub(super) use descriptors::{
+    ABSOLUTE_WHEEL_MOUSE_REPORT_DESCRIPTOR, BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+    CONSUMER_CONTROL_REPORT_DESCRIPTOR, NKRO_KEYBOARD_REPORT_DESCRIPTOR,
+    RELATIVE_WHEEL_MOUSE_REPORT_DESCRIPTOR,
+};
+
+// Large enough for every report `SynergyHid` makes
+pub const MAX_REPORT_SIZE: usize = NKRO_REPORT_SIZE;
+
+// The server's wheel deltas are in 1/120 of a notch
+const WHEEL_DELTA: i32 = 120;
+// Physical maximum of the Resolution Multipliers in the mouse descriptors
+const WHEEL_RESOLUTION_MULTIPLIER: i32 = 8;
+
+// Modifier bits of the mask the server sends with key events
+pub const MODIFIER_SHIFT: u16 = 0x0001;
+pub const MODIFIER_CONTROL: u16 = 0x0002;
+pub const MODIFIER_ALT: u16 = 0x0004;
+pub const MODIFIER_META: u16 = 0x0008;
+pub const MODIFIER_SUPER: u16 = 0x0010;
+pub const MODIFIER_ALT_GR: u16 = 0x0020;
+
+// Lock state bits of the modifier mask the server sends with key events and CINN
+pub const MODIFIER_CAPS_LOCK: u16 = 0x1000;
+pub const MODIFIER_NUM_LOCK: u16 = 0x2000;
+pub const MODIFIER_SCROLL_LOCK: u16 = 0x4000;
+
+// Special keys live in the private use area, the other key ids are characters
+const SPECIAL_KEYS: core::ops::RangeInclusive<u16> = 0xE000..=0xF8FF;
+
+const LOCK_KEYS: [(u16, u8); 3] = [
+    (MODIFIER_CAPS_LOCK, HID_KEY_CAPS_LOCK),
+    (MODIFIER_NUM_LOCK, HID_KEY_NUM_LOCK),
+    (MODIFIER_SCROLL_LOCK, HID_KEY_SCROLL_LOCK),
+];
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReportType {
+    Keyboard = 1,
+    Mouse = 2,
+    Consumer = 3,
+}
+
+/**
+ * How the mouse interface reports the pointer. Absolute positions land exactly where the server
+ * puts the cursor, but some hosts (BIOS setup, games that capture the mouse, some VMs and KVMs)
+ * only understand a plain relative mouse.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MouseMode {
+    Absolute,
+    Relative,
+}
+
+/**
+ * Keyboard report layout. The boot report holds six keys besides the modifiers and reports a
+ * rollover error past that, the NKRO bitmap holds every key. Hosts using the boot protocol
+ * always get the boot report.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyboardMode {
+    SixKey,
+    Nkro,
+}
+
+/**
+ * Report format the host picked with SET_PROTOCOL, the values are the ones of the request. Hosts
+ * start with the report protocol, firmware setup screens and bootloaders that don't parse report
+ * descriptors switch boot devices to the boot protocol.
+ */
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Protocol {
+    Boot = 0,
+    Report = 1,
+}
+
+/**
+ * Splits a relative move into steps that fit the i8 fields of the relative mouse report.
+ */
+pub fn split_move(x: i32, y: i32) -> impl Iterator<Item = (i8, i8)> {
+    let (mut x, mut y) = (x, y);
+    core::iter::from_fn(move || {
+        if x == 0 && y == 0 {
+            return None;
+        }
+        let dx = x.clamp(-127, 127);
+        let dy = y.clamp(-127, 127);
+        (x, y) = (x - dx, y - dy);
+        Some((dx as i8, dy as i8))
+    })
+}
+
+#[derive(Debug)]
+pub struct SynergyHid {
+    flip_mouse_wheel: bool,
+    layout: Layout,
+    unicode_input: Option<UnicodeInput>,
+    scan_codes: Option<ScanCodes>,
+    // Keyboard states left to send for a character typed with Unicode input
+    unicode_steps: UnicodeSteps,
+    // Keys that were down before it, they are pressed again after it
+    unicode_saved: Option<KeyboardReport>,
+    keyboard_mode: KeyboardMode,
+    keyboard_protocol: Protocol,
+    mouse_mode: MouseMode,
+    mouse_protocol: Protocol,
+    // Resolution Multiplier feature report, bits 0-1 for the wheel and 2-3 for the pan
+    resolution_multiplier: u8,
+    // Scrolling that didn't make a whole step yet, in the server's units
+    scroll_remainder: (i32, i32),
+    x: u16,
+    y: u16,
+    server_buttons: [u16; 512],
+    // Button of the last key that set the modifier override of the keyboard report
+    override_button: Option<u16>,
+    // Lock bits the server has set in a mask, servers that don't track a lock never set it
+    server_locks: u16,
+
+    // Report 1
+    keyboard_report: KeyboardReport,
+    // Report 2, one of them depending on the mouse mode
+    mouse_report: AbsMouseReport,
+    rel_mouse_report: RelMouseReport,
+    // Report 3
+    consumer_report: ConsumerReport,
+}
+
+impl SynergyHid {
+    pub fn new(flip_mouse_wheel: bool) -> Self {
+        Self {
+            flip_mouse_wheel,
+            layout: Layout::Us,
+            unicode_input: None,
+            scan_codes: None,
+            unicode_steps: UnicodeSteps::default(),
+            unicode_saved: None,
+            keyboard_mode: KeyboardMode::SixKey,
+            keyboard_protocol: Protocol::Report,
+            mouse_mode: MouseMode::Absolute,
+            mouse_protocol: Protocol::Report,
+            resolution_multiplier: 0,
+            scroll_remainder: (0, 0),
+            x: 0,
+            y: 0,
+            server_buttons: [0; 512],
+            override_button: None,
+            server_locks: MODIFIER_CAPS_LOCK,
+            keyboard_report: KeyboardReport::default(),
+            mouse_report: AbsMouseReport::default(),
+            rel_mouse_report: RelMouseReport::default(),
+            consumer_report: ConsumerReport::default(),
+        }
+    }
+
+    pub const fn get_report_descriptor(report_type: ReportType) -> (u8, &'static [u8]) {
+        match report_type {
+            ReportType::Keyboard => (8, BOOT_KEYBOARD_REPORT_DESCRIPTOR),
+            ReportType::Mouse => (7, ABSOLUTE_WHEEL_MOUSE_REPORT_DESCRIPTOR),
+            ReportType::Consumer => (2, CONSUMER_CONTROL_REPORT_DESCRIPTOR),
+        }
+    }
+
+    pub const fn get_keyboard_report_descriptor(
+        keyboard_mode: KeyboardMode,
+    ) -> (u8, &'static [u8]) {
+        match keyboard_mode {
+            KeyboardMode::SixKey => Self::get_report_descriptor(ReportType::Keyboard),
+            KeyboardMode::Nkro => (NKRO_REPORT_SIZE as u8, NKRO_KEYBOARD_REPORT_DESCRIPTOR),
+        }
+    }
+
+    pub const fn get_mouse_report_descriptor(mouse_mode: MouseMode) -> (u8, &'static [u8]) {
+        match mouse_mode {
+            MouseMode::Absolute => Self::get_report_descriptor(ReportType::Mouse),
+            MouseMode::Relative => (5, RELATIVE_WHEEL_MOUSE_REPORT_DESCRIPTOR),
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    // Must match the layout the host is set to, the keys that are down are kept
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    pub fn unicode_input(&self) -> Option<UnicodeInput> {
+        self.unicode_input
+    }
+
+    // Characters the layout can't type are dropped without it
+    pub fn set_unicode_input(&mut self, unicode_input: Option<UnicodeInput>) {
+        self.unicode_input = unicode_input;
+    }
+
+    pub fn scan_codes(&self) -> Option<ScanCodes> {
+        self.scan_codes
+    }
+
+    /**
+     * Maps the button of key events instead of their key id when set, the layout, the Unicode
+     * input and the modifier mask aren't used then. Media keys still go by key id.
+     */
+    pub fn set_scan_codes(&mut self, scan_codes: Option<ScanCodes>) {
+        self.scan_codes = scan_codes;
+    }
+
+    pub fn keyboard_mode(&self) -> KeyboardMode {
+        self.keyboard_mode
+    }
+
+    // Must match the descriptor the keyboard interface was created with
+    pub fn set_keyboard_mode(&mut self, keyboard_mode: KeyboardMode) {
+        self.keyboard_mode = keyboard_mode;
+    }
+
+    pub fn set_keyboard_protocol(&mut self, protocol: Protocol) {
+        self.keyboard_protocol = protocol;
+    }
+
+    pub fn mouse_mode(&self) -> MouseMode {
+        self.mouse_mode
+    }
+
+    // Must match the descriptor the mouse interface was created with
+    pub fn set_mouse_mode(&mut self, mouse_mode: MouseMode) {
+        self.mouse_mode = mouse_mode;
+    }
+
+    pub fn set_mouse_protocol(&mut self, protocol: Protocol) {
+        self.mouse_protocol = protocol;
+    }
+
+    // Set by the host to get the wheel and the pan in fractions of a notch
+    pub fn set_resolution_multiplier(&mut self, report: u8) {
+        self.resolution_multiplier = report;
+    }
+
+    /**
+     * Whether the cursor has to be moved with `mouse_move`, in relative mode or when the host uses
+     * the boot protocol, which has no absolute pointer.
+     */
+    pub fn relative_pointer(&self) -> bool {
+        self.mouse_mode == MouseMode::Relative || self.mouse_protocol == Protocol::Boot
+    }
+
+    pub fn key_down<'a>(
+        &mut self,
+        key: u16,
+        mask: u16,
+        button: u16,
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        debug!("Key down {} {} {}", key, mask, button);
+        self.track_locks(mask);
+        match self.server_buttons.get_mut(button as usize) {
+            Some(pressed) => *pressed = key,
+            None => warn!("Button {} out of range", button),
+        }
+        let hid = self.event_code(key, button);
+        // debug!("Key Down {:#04x} -> Keycode: {:?}", key, hid);
+        match hid {
+            KeyCode::None
+                if self
+                    .unicode_char(key)
+                    .is_some_and(|c| self.start_unicode(c)) =>
+            {
+                let boot = self.next_unicode_step().unwrap_or_default();
+                self.keyboard(boot, report)
+            }
+            KeyCode::None => {
+                warn!("Keycode not found");
+                let boot = self.keyboard_report.clear();
+                self.keyboard(boot, report)
+            }
+            // The override of the key that is down stays until it goes up
+            KeyCode::Key(code) if (HID_KEY_CONTROL_LEFT..=HID_KEY_GUI_RIGHT).contains(&code) => {
+                self.keyboard_report.press(code);
+                let boot = self
+                    .keyboard_report
+                    .show_modifier(1 << (code - HID_KEY_CONTROL_LEFT));
+                self.keyboard(boot, report)
+            }
+            KeyCode::Key(code) => {
+                self.keyboard_report.press(code);
+                let (extra, suppressed) = self.mask_override(key, mask);
+                self.override_button = Some(button);
+                let boot = self
+                    .keyboard_report
+                    .set_modifier_override(extra, suppressed);
+                self.keyboard(boot, report)
+            }
+            KeyCode::Consumer(key) => {
+                report[..2].copy_from_slice(&self.consumer_report.press(key));
+                (ReportType::Consumer, &report[0..2])
+            }
+        }
+    }
+
+    pub fn key_up<'a>(
+        &mut self,
+        key: u16,
+        mask: u16,
+        button: u16,
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        debug!("Key up {} {} {}", key, mask, button);
+        self.track_locks(mask);
+        let key = self
+            .server_buttons
+            .get(button as usize)
+            .copied()
+            .unwrap_or(0);
+        let hid = if key != 0 {
+            // debug!("Key {key} up");
+            self.server_buttons[button as usize] = 0;
+            self.event_code(key, button)
+        } else if key == 0 {
+            debug!("Key 0 up, clear all key down");
+            KeyCode::None
+        } else {
+            // warn!("Key {key} up with no key down");
+            KeyCode::None
+        };
+        // debug!("Key Down {:#04x} -> Keycode: {:?}", key, hid);
+        if self.override_button == Some(button) {
+            self.override_button = None;
+            self.keyboard_report.set_modifier_override(0, 0);
+        }
+        match hid {
+            // Typed with Unicode input on key down, nothing is left to release
+            KeyCode::None if key != 0 && self.unicode_char(key).is_some() => {
+                let boot = self.keyboard_report.send();
+                self.keyboard(boot, report)
+            }
+            KeyCode::None => {
+                warn!("Keycode not found");
+                let boot = self.keyboard_report.clear();
+                self.keyboard(boot, report)
+            }
+            KeyCode::Key(key) => {
+                let boot = self.keyboard_report.release(key);
+                self.keyboard(boot, report)
+            }
+            KeyCode::Consumer(_key) => {
+                report[..2].copy_from_slice(&self.consumer_report.release());
+                (ReportType::Consumer, &report[0..2])
+            }
+        }
+    }
+
+    /**
+     * Presses the key and the modifiers that type the character, or starts typing it with Unicode
+     * input when the layout can't. None if neither can.
+     */
+    pub fn char_down<'a>(
+        &mut self,
+        c: char,
+        report: &'a mut [u8],
+    ) -> Option<(ReportType, &'a [u8])> {
+        let boot = match self.typed_key(c) {
+            Some((key, modifiers)) => {
+                self.keyboard_report.press(key);
+                self.keyboard_report.set_modifier_override(modifiers, 0)
+            }
+            None if self.start_unicode(c) => self.next_unicode_step()?,
+            None => return None,
+        };
+        Some(self.keyboard(boot, report))
+    }
+
+    pub fn char_up<'a>(&mut self, c: char, report: &'a mut [u8]) -> Option<(ReportType, &'a [u8])> {
+        let (key, _) = self.typed_key(c)?;
+        self.keyboard_report.set_modifier_override(0, 0);
+        let boot = self.keyboard_report.release(key);
+        Some(self.keyboard(boot, report))
+    }
+
+    /**
+     * Next report of a character typed with Unicode input, to send after the one `key_down` or
+     * `char_down` returned until it's None. The keys that were down before are down again by then.
+     */
+    pub fn next_report<'a>(&mut self, report: &'a mut [u8]) -> Option<(ReportType, &'a [u8])> {
+        let boot = self.next_unicode_step()?;
+        Some(self.keyboard(boot, report))
+    }
+
+    /**
+     * Presses the lock keys whose state on the host, as shown by its keyboard LEDs, differs from
+     * the server's modifier mask. None if they all match already.
+     * Num Lock and Scroll Lock are left alone until the server has set them in a mask, macOS for
+     * one never does and would turn them off on the host every time the cursor enters.
+     */
+    pub fn lock_keys_down<'a>(
+        &mut self,
+        mask: u16,
+        leds: KeyboardLeds,
+        report: &'a mut [u8],
+    ) -> Option<(ReportType, &'a [u8])> {
+        self.track_locks(mask);
+        let host = [leds.caps_lock, leds.num_lock, leds.scroll_lock];
+        let mut ret = None;
+        for ((modifier, key), on) in LOCK_KEYS.into_iter().zip(host) {
+            if self.server_locks & modifier != 0 && (mask & modifier != 0) != on {
+                ret = Some(self.keyboard_report.press(key));
+            }
+        }
+        let boot = ret?;
+        Some(self.keyboard(boot, report))
+    }
+
+    fn track_locks(&mut self, mask: u16) {
+        for (modifier, _) in LOCK_KEYS {
+            self.server_locks |= mask & modifier;
+        }
+    }
+
+    // Releases the lock keys pressed by `lock_keys_down`
+    pub fn lock_keys_up<'a>(&mut self, report: &'a mut [u8]) -> (ReportType, &'a [u8]) {
+        let mut ret = [0; 8];
+        for (_, key) in LOCK_KEYS {
+            ret = self.keyboard_report.release(key);
+        }
+        self.keyboard(ret, report)
+    }
+
+    pub fn set_cursor_position<'a>(
+        &mut self,
+        x: u16,
+        y: u16,
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        (self.x, self.y) = (x, y);
+        report[..7].copy_from_slice(&self.mouse_report.move_to(x, y));
+        (ReportType::Mouse, &report[..7])
+    }
+
+    // Relative report, only when `relative_pointer` is set
+    pub fn mouse_move<'a>(&mut self, x: i8, y: i8, report: &'a mut [u8]) -> (ReportType, &'a [u8]) {
+        let relative = self.rel_mouse_report.move_by(x, y);
+        let data = match self.mouse_protocol {
+            Protocol::Boot => &relative[..3],
+            Protocol::Report => &relative,
+        };
+        report[..data.len()].copy_from_slice(data);
+        (ReportType::Mouse, &report[..data.len()])
+    }
+
+    pub fn mouse_down<'a>(&mut self, button: i8, report: &'a mut [u8]) -> (ReportType, &'a [u8]) {
+        let button = synergy_mouse_button(button);
+        let absolute = self.mouse_report.mouse_down(button);
+        let relative = self.rel_mouse_report.mouse_down(button);
+        self.mouse(absolute, relative, report)
+    }
+
+    pub fn mouse_up<'a>(&mut self, button: i8, report: &'a mut [u8]) -> (ReportType, &'a [u8]) {
+        let button = synergy_mouse_button(button);
+        let absolute = self.mouse_report.mouse_up(button);
+        let relative = self.rel_mouse_report.mouse_up(button);
+        self.mouse(absolute, relative, report)
+    }
+
+    pub fn mouse_scroll<'a>(
+        &mut self,
+        x: i16,
+        y: i16,
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        let pan_step = self.scroll_step(self.resolution_multiplier & 0x0C != 0);
+        let wheel_step = self.scroll_step(self.resolution_multiplier & 0x03 != 0);
+        // Trackpads send a fraction of a notch at a time, the rest waits for the next delta
+        let x = self.scroll_remainder.0 + x as i32;
+        let y = self.scroll_remainder.1 + y as i32;
+        self.scroll_remainder = (x % pan_step, y % wheel_step);
+        let mut x = (x / pan_step).clamp(-127, 127) as i8;
+        let mut y = (y / wheel_step).clamp(-127, 127) as i8;
+        if self.flip_mouse_wheel {
+            x = x.saturating_neg();
+            y = y.saturating_neg();
+        }
+        let absolute = self.mouse_report.mouse_wheel(y, x);
+        let relative = self.rel_mouse_report.mouse_wheel(y, x);
+        self.mouse(absolute, relative, report)
+    }
+
+    pub fn clear<'a>(
+        &mut self,
+        report_type: ReportType,
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        match report_type {
+            ReportType::Keyboard => {
+                self.unicode_steps = UnicodeSteps::default();
+                self.unicode_saved = None;
+                let boot = self.keyboard_report.clear();
+                self.keyboard(boot, report)
+            }
+            ReportType::Mouse => {
+                self.scroll_remainder = (0, 0);
+                let absolute = self.mouse_report.clear();
+                let relative = self.rel_mouse_report.clear();
+                self.mouse(absolute, relative, report)
+            }
+            ReportType::Consumer => {
+                report[..2].copy_from_slice(&self.consumer_report.clear());
+                (ReportType::Consumer, &report[..2])
+            }
+        }
+    }
+
+    /**
+     * HID key of a key id and the modifiers the layout needs for it. Characters go through the
+     * layout, letters keep the case the mask's Shift and the host's Caps Lock give them.
+     */
+    fn key_code(&self, id: u16) -> (KeyCode, u8) {
+        if SPECIAL_KEYS.contains(&id) {
+            return (synergy_to_hid(id), 0);
+        }
+        match char::from_u32(id as u32).and_then(|c| Some((c, self.layout.lookup(c)?))) {
+            Some((c, (key, modifiers))) if c.is_alphabetic() => {
+                (KeyCode::Key(key), modifiers & !MODIFIER_BIT_SHIFT)
+            }
+            Some((_, (key, modifiers))) => (KeyCode::Key(key), modifiers),
+            None => (KeyCode::None, 0),
+        }
+    }
+
+    // HID key of a key event, from its button when mapping scan codes
+    fn event_code(&self, id: u16, button: u16) -> KeyCode {
+        let Some(scan_codes) = self.scan_codes else {
+            return self.key_code(id).0;
+        };
+        match synergy_to_hid(id) {
+            KeyCode::Consumer(key) if SPECIAL_KEYS.contains(&id) => KeyCode::Consumer(key),
+            _ => scan_codes
+                .hid_key(button)
+                .map_or(KeyCode::None, KeyCode::Key),
+        }
+    }
+
+    /**
+     * Modifiers to add to the modifier keys that are down and the ones to hide while the key is
+     * the last one pressed, so the host sees the server's mask whatever order the modifier keys
+     * came in. Characters take Shift and AltGr from the layout instead, letters only AltGr. Scan
+     * codes are sent as they are.
+     */
+    fn mask_override(&self, id: u16, mask: u16) -> (u8, u8) {
+        if self.scan_codes.is_some() {
+            return (0, 0);
+        }
+        let has = |modifier| mask & modifier != 0;
+        let layout = self.key_code(id).1;
+        let character = !SPECIAL_KEYS.contains(&id);
+        let letter = char::from_u32(id as u32).is_some_and(char::is_alphabetic);
+        let alt_gr = match character {
+            true => layout & MODIFIER_BIT_ALT_GR != 0,
+            false => has(MODIFIER_ALT_GR),
+        };
+        let shift = match character && !letter {
+            true => layout & MODIFIER_BIT_SHIFT != 0,
+            false => has(MODIFIER_SHIFT),
+        };
+        // Report bits of both hands and the one to add, AltGr is the right Alt
+        let wanted = [
+            (0x11, MODIFIER_BIT_CONTROL, has(MODIFIER_CONTROL) && !alt_gr),
+            (0x22, MODIFIER_BIT_SHIFT, shift),
+            (0x04, MODIFIER_BIT_ALT, has(MODIFIER_ALT) && !alt_gr),
+            (0x88, MODIFIER_BIT_GUI, has(MODIFIER_META | MODIFIER_SUPER)),
+            (0x40, MODIFIER_BIT_ALT_GR, alt_gr),
+        ];
+        let down = self.keyboard_report.modifier();
+        wanted
+            .into_iter()
+            .fold((0, 0), |(extra, suppressed), (bits, bit, on)| match on {
+                true if down & bits == 0 => (extra | bit, suppressed),
+                true => (extra, suppressed),
+                false => (extra, suppressed | bits),
+            })
+    }
+
+    // Character of a key id that goes through Unicode input when the layout has no key for it
+    fn unicode_char(&self, id: u16) -> Option<char> {
+        self.unicode_input?;
+        if SPECIAL_KEYS.contains(&id) || self.scan_codes.is_some() {
+            return None;
+        }
+        char::from_u32(id as u32).filter(|c| !c.is_control())
+    }
+
+    // The keys that are down are released while the character is typed
+    fn start_unicode(&mut self, c: char) -> bool {
+        let Some(steps) = self.unicode_input.and_then(|i| i.steps(self.layout, c)) else {
+            return false;
+        };
+        self.unicode_steps = steps;
+        if self.unicode_saved.is_none() {
+            self.unicode_saved = Some(self.keyboard_report.clone());
+        }
+        true
+    }
+
+    fn next_unicode_step(&mut self) -> Option<[u8; 8]> {
+        match self.unicode_steps.next() {
+            Some((key, modifier)) => {
+                self.keyboard_report.clear();
+                self.keyboard_report.press(key);
+                Some(self.keyboard_report.set_modifier_override(modifier, 0))
+            }
+            None => {
+                self.keyboard_report = self.unicode_saved.take()?;
+                Some(self.keyboard_report.send())
+            }
+        }
+    }
+
+    // Tab and Enter come from pasted text, the layout doesn't move them
+    fn typed_key(&self, c: char) -> Option<(u8, u8)> {
+        match c {
+            '\t' => Some((HID_KEY_TAB, 0)),
+            '\n' => Some((HID_KEY_ENTER, 0)),
+            c => self.layout.lookup(c),
+        }
+    }
+
+    // Server units per wheel or pan step, steps are 1/8 notch in high resolution
+    fn scroll_step(&self, high_resolution: bool) -> i32 {
+        if high_resolution && self.mouse_protocol == Protocol::Report {
+            WHEEL_DELTA / WHEEL_RESOLUTION_MULTIPLIER
+        } else {
+            WHEEL_DELTA
+        }
+    }
+
+    // The boot report of the keys is given, the NKRO one is made from the same state when needed
+    fn keyboard<'a>(&self, boot: [u8; 8], report: &'a mut [u8]) -> (ReportType, &'a [u8]) {
+        let nkro;
+        let data: &[u8] = match (self.keyboard_protocol, self.keyboard_mode) {
+            (Protocol::Report, KeyboardMode::Nkro) => {
+                nkro = self.keyboard_report.nkro();
+                &nkro
+            }
+            _ => &boot,
+        };
+        report[..data.len()].copy_from_slice(data);
+        (ReportType::Keyboard, &report[..data.len()])
+    }
+
+    // Both mouse reports track the buttons, only the one of the current mode is sent. The boot
+    // report is the buttons and the moves of the relative one.
+    fn mouse<'a>(
+        &self,
+        absolute: [u8; 7],
+        relative: [u8; 5],
+        report: &'a mut [u8],
+    ) -> (ReportType, &'a [u8]) {
+        let data: &[u8] = match (self.mouse_protocol, self.mouse_mode) {
+            (Protocol::Boot, _) => &relative[..3],
+            (Protocol::Report, MouseMode::Absolute) => &absolute,
+            (Protocol::Report, MouseMode::Relative) => &relative,
+        };
+        report[..data.len()].copy_from_slice(data);
+        (ReportType::Mouse, &report[..data.len()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        keycodes::{
+            HID_KEY_0, HID_KEY_1, HID_KEY_A, HID_KEY_ARROW_LEFT, HID_KEY_B, HID_KEY_BRACKET_RIGHT,
+            HID_KEY_C, HID_KEY_CAPS_LOCK, HID_KEY_E, HID_KEY_ENTER, HID_KEY_NUM_LOCK, HID_KEY_Q,
+            HID_KEY_SCROLL_LOCK, HID_KEY_SEMICOLON, HID_KEY_U, HID_KEY_Y, HID_KEY_Z,
+        },
+        split_move, KeyboardLeds, KeyboardMode, Layout, MouseMode, Protocol, ReportType, ScanCodes,
+        UnicodeInput, MAX_REPORT_SIZE, MODIFIER_CAPS_LOCK, MODIFIER_NUM_LOCK, MODIFIER_SCROLL_LOCK,
+    };
+
+    #[test]
+    fn test_key() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.key_down(0x0000, 0x0000, 0x0000, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down('A' as u16, 0x0000, 0x0000, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_A, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+
+        assert_eq!(
+            hid.key_down('B' as u16, 0x0000, 0x0000, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_A, HID_KEY_B, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('B' as u16, 0x0000, 0x0000, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_A, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        // Wrong key up, report is cleared
+        assert_eq!(
+            hid.key_up('C' as u16, 0x0000, 0x0000, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+
+        // kKeyAudioMute(0xE0AD) -> HID_USAGE_CONSUMER_MUTE(0x00E2), little endian
+        assert_eq!(
+            hid.key_down(0xE0AD, 0x0000, 1, &mut report),
+            (ReportType::Consumer, [0xE2, 0x00].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_layout(Layout::De);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // Letters move, their case is up to the host's Shift and Caps Lock
+        assert_eq!(
+            hid.key_down('Z' as u16, 0x0000, 1, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_Y, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        // AltGr stays down as long as a key that needs it
+        assert_eq!(
+            hid.key_down('@' as u16, 0x0000, 2, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x40, 0, HID_KEY_Y, HID_KEY_Q, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('@' as u16, 0x0000, 2, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_Y, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_down('=' as u16, 0x0000, 2, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_Y, HID_KEY_0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('=' as u16, 0x0000, 2, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_Y, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        // A dead key on the host, the character is dropped
+        assert_eq!(
+            hid.key_down('^' as u16, 0x0000, 3, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_char() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_layout(Layout::De);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.char_down('Y', &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_Z, 0, 0, 0, 0, 0].as_ref()
+            ))
+        );
+        assert_eq!(
+            hid.char_up('Y', &mut report),
+            Some((ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref()))
+        );
+        assert_eq!(
+            hid.char_down('\n', &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_ENTER, 0, 0, 0, 0, 0].as_ref()
+            ))
+        );
+        assert_eq!(hid.char_down('`', &mut report), None);
+    }
+
+    #[test]
+    fn test_unicode_input() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        hid.key_down(0xEFE1, 0x0001, 1, &mut report);
+        assert_eq!(
+            hid.key_down('€' as u16, 0x0001, 2, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+
+        hid.set_unicode_input(Some(UnicodeInput::Windows));
+        hid.key_down(0xEFE1, 0x0001, 1, &mut report);
+        // Shift is let go while typing, Alt+Numpad+ starts the code
+        assert_eq!(
+            hid.key_down('€' as u16, 0x0001, 2, &mut report),
+            (ReportType::Keyboard, [0x04, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        let mut reports = Vec::new();
+        while let Some((_, r)) = hid.next_report(&mut report) {
+            reports.push(r.to_vec());
+        }
+        assert_eq!(reports.len(), 12);
+        assert_eq!(reports[0], [0x04, 0, 0x57, 0, 0, 0, 0, 0]);
+        assert_eq!(reports[10], [0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(reports[11], [0x02, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            hid.key_up('€' as u16, 0x0001, 2, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+
+        // Characters from pasted text too
+        hid.set_unicode_input(Some(UnicodeInput::Linux));
+        assert_eq!(
+            hid.char_down('é', &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0x03, 0, HID_KEY_U, 0, 0, 0, 0, 0].as_ref()
+            ))
+        );
+        assert_eq!(hid.char_up('é', &mut report), None);
+        // Key ids of special keys never are characters
+        hid.clear(ReportType::Keyboard, &mut report);
+        assert_eq!(hid.next_report(&mut report), None);
+        assert_eq!(
+            hid.key_down(0xE0FF, 0x0000, 3, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(hid.next_report(&mut report), None);
+    }
+
+    #[test]
+    fn test_mask_symbols() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // Shift is in the mask before its key event comes
+        assert_eq!(
+            hid.key_down('!' as u16, 0x0001, 10, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_1, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('!' as u16, 0x0001, 10, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // A server with another layout shifts ';', the host doesn't
+        assert_eq!(
+            hid.key_down(0xEFE1, 0x0000, 50, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down(';' as u16, 0x0001, 47, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_SEMICOLON, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up(';' as u16, 0x0001, 47, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down(':' as u16, 0x0001, 47, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_SEMICOLON, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+    }
+
+    #[test]
+    fn test_mask_alt_gr() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_layout(Layout::De);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // AltGr of a Windows server comes with Control, which would make a shortcut of the character
+        assert_eq!(
+            hid.key_down(0xEFE3, 0x0000, 37, &mut report),
+            (ReportType::Keyboard, [0x01, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down(0xEFEA, 0x0002, 108, &mut report),
+            (ReportType::Keyboard, [0x41, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down('€' as u16, 0x0026, 26, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x40, 0, HID_KEY_E, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('€' as u16, 0x0026, 26, &mut report),
+            (ReportType::Keyboard, [0x41, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_up(0xEFEA, 0x0026, 108, &mut report),
+            (ReportType::Keyboard, [0x01, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_up(0xEFE3, 0x0002, 37, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Shift+2 on a US server, AltGr+Q on the host
+        assert_eq!(
+            hid.key_down(0xEFE1, 0x0000, 50, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down('@' as u16, 0x0001, 11, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x40, 0, HID_KEY_Q, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('@' as u16, 0x0001, 11, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_mask_shortcuts() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_layout(Layout::De);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // Control is in the mask before its key event comes
+        assert_eq!(
+            hid.key_down('c' as u16, 0x0002, 54, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x01, 0, HID_KEY_C, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('c' as u16, 0x0002, 54, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Letters take Shift from the mask
+        assert_eq!(
+            hid.key_down('Z' as u16, 0x0003, 52, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x03, 0, HID_KEY_Y, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('Z' as u16, 0x0003, 52, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Control+Plus has Shift on a US server, not on the host
+        assert_eq!(
+            hid.key_down('+' as u16, 0x0003, 21, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x01, 0, HID_KEY_BRACKET_RIGHT, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('+' as u16, 0x0003, 21, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Special keys take every modifier from the mask
+        assert_eq!(
+            hid.key_down(0xEF51, 0x0013, 113, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x0B, 0, HID_KEY_ARROW_LEFT, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        // Modifier keys pressed while it's down add to its mask
+        assert_eq!(
+            hid.key_down(0xEFE9, 0x0013, 64, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x0F, 0, HID_KEY_ARROW_LEFT, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up(0xEFE9, 0x0017, 64, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x0B, 0, HID_KEY_ARROW_LEFT, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up(0xEF51, 0x0013, 113, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_scan_codes() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_layout(Layout::De);
+        hid.set_unicode_input(Some(UnicodeInput::Linux));
+        hid.set_scan_codes(Some(ScanCodes::X11));
+        let mut report = [0; MAX_REPORT_SIZE];
+        // Left Shift and the key of 1 on a US server, the host's layout decides what they type
+        assert_eq!(
+            hid.key_down(0xEFE1, 0x0000, 50, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.key_down('!' as u16, 0x0001, 10, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_1, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(
+            hid.key_up('!' as u16, 0x0001, 10, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Z stays where it is on the server, the character isn't typed with Unicode input
+        assert_eq!(
+            hid.key_down('Ż' as u16, 0x0001, 52, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_Z, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        assert_eq!(hid.next_report(&mut report), None);
+        assert_eq!(
+            hid.key_up('Ż' as u16, 0x0001, 52, &mut report),
+            (ReportType::Keyboard, [0x02, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+        // Media keys go by key id
+        assert_eq!(
+            hid.key_down(0xE0AD, 0x0001, 121, &mut report),
+            (ReportType::Consumer, [0xE2, 0x00].as_ref())
+        );
+    }
+
+    // Seven keys down, as in a chorded shortcut
+    fn press_seven(hid: &mut super::SynergyHid) -> Vec<u8> {
+        let mut report = [0; MAX_REPORT_SIZE];
+        hid.key_down(0xEFE1, 0x0000, 1, &mut report);
+        for (i, c) in "abcdef".chars().enumerate() {
+            hid.key_down(c as u16, 0x0001, 2 + i as u16, &mut report);
+        }
+        let (_, r) = hid.key_down('g' as u16, 0x0001, 8, &mut report);
+        r.to_vec()
+    }
+
+    #[test]
+    fn test_rollover() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // The modifiers are still reported, the keys are not guessed
+        assert_eq!(press_seven(&mut hid), [0x02, 0, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(
+            hid.key_up('b' as u16, 0x0001, 3, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_A, 0x06, 0x07, 0x08, 0x09, 0x0A].as_ref()
+            )
+        );
+    }
+
+    #[test]
+    fn test_nkro() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_keyboard_mode(KeyboardMode::Nkro);
+        let mut expected = vec![0; 29];
+        // Shift, then A (0x04) to G (0x0A)
+        expected[0] = 0x02;
+        expected[1] = 0xF0;
+        expected[2] = 0x07;
+        assert_eq!(press_seven(&mut hid), expected);
+
+        // The same keys in the boot protocol
+        hid.set_keyboard_protocol(Protocol::Boot);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.key_up('g' as u16, 0x0001, 8, &mut report),
+            (
+                ReportType::Keyboard,
+                [0x02, 0, HID_KEY_A, HID_KEY_B, 0x06, 0x07, 0x08, 0x09].as_ref()
+            )
+        );
+    }
+
+    #[test]
+    fn test_lock_keys() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        let leds = KeyboardLeds {
+            caps_lock: true,
+            ..KeyboardLeds::default()
+        };
+        assert_eq!(
+            hid.lock_keys_down(MODIFIER_CAPS_LOCK, leds, &mut report),
+            None
+        );
+        // Caps lock is only on on the host, num lock only on the server
+        assert_eq!(
+            hid.lock_keys_down(MODIFIER_NUM_LOCK, leds, &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_CAPS_LOCK, HID_KEY_NUM_LOCK, 0, 0, 0, 0].as_ref()
+            ))
+        );
+        assert_eq!(
+            hid.lock_keys_up(&mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+
+        // Once the server has shown it tracks num lock, it is turned off on the host too
+        let leds = KeyboardLeds {
+            num_lock: true,
+            ..KeyboardLeds::default()
+        };
+        assert_eq!(
+            hid.lock_keys_down(0, leds, &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_NUM_LOCK, 0, 0, 0, 0, 0].as_ref()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_untracked_lock_keys() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // A server that never sets num lock or scroll lock leaves them as they are on the host
+        let leds = KeyboardLeds {
+            num_lock: true,
+            scroll_lock: true,
+            ..KeyboardLeds::default()
+        };
+        assert_eq!(hid.lock_keys_down(0, leds, &mut report), None);
+        hid.key_down('a' as u16, 0, 30, &mut report);
+        hid.key_up('a' as u16, 0, 30, &mut report);
+        assert_eq!(hid.lock_keys_down(0, leds, &mut report), None);
+
+        // Seen in a key event, scroll lock is turned off from then on
+        hid.key_down('a' as u16, MODIFIER_SCROLL_LOCK, 30, &mut report);
+        hid.key_up('a' as u16, MODIFIER_SCROLL_LOCK, 30, &mut report);
+        assert_eq!(
+            hid.lock_keys_down(MODIFIER_SCROLL_LOCK, leds, &mut report),
+            None
+        );
+        assert_eq!(
+            hid.lock_keys_down(0, leds, &mut report),
+            Some((
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_SCROLL_LOCK, 0, 0, 0, 0, 0].as_ref()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_keyboard_leds() {
+        assert_eq!(KeyboardLeds::from_report(0), KeyboardLeds::default());
+        assert_eq!(
+            KeyboardLeds::from_report(0x12),
+            KeyboardLeds {
+                caps_lock: true,
+                kana: true,
+                ..KeyboardLeds::default()
+            }
+        );
+        // Constant padding bits are ignored
+        assert_eq!(
+            KeyboardLeds::from_report(0xE5),
+            KeyboardLeds {
+                num_lock: true,
+                scroll_lock: true,
+                ..KeyboardLeds::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_button_out_of_range() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.key_down('A' as u16, 0x0000, 0xFFFF, &mut report),
+            (
+                ReportType::Keyboard,
+                [0, 0, HID_KEY_A, 0, 0, 0, 0, 0].as_ref()
+            )
+        );
+        // Not remembered, so the key up clears the report
+        assert_eq!(
+            hid.key_up('A' as u16, 0x0000, 0xFFFF, &mut report),
+            (ReportType::Keyboard, [0, 0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_flipped_wheel_limit() {
+        let mut hid = super::SynergyHid::new(true);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.mouse_scroll(-128 * 120, -128 * 120, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 127, 127].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_scroll_remainder() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        for _ in 0..3 {
+            assert_eq!(
+                hid.mouse_scroll(0, 30, &mut report),
+                (ReportType::Mouse, [0, 0, 0, 0, 0, 0, 0].as_ref())
+            );
+        }
+        assert_eq!(
+            hid.mouse_scroll(-60, 30, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 1, 0].as_ref())
+        );
+        assert_eq!(
+            hid.mouse_scroll(-60, 0, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 0, 0xFF].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_high_resolution_scroll() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        // Only the wheel multiplier is on, 15 units per step
+        hid.set_resolution_multiplier(0x01);
+        assert_eq!(
+            hid.mouse_scroll(40, 40, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 2, 0].as_ref())
+        );
+        assert_eq!(
+            hid.mouse_scroll(80, 5, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 1, 1].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_relative_mouse() {
+        let mut hid = super::SynergyHid::new(false);
+        hid.set_mouse_mode(MouseMode::Relative);
+        let mut report = [0; MAX_REPORT_SIZE];
+        assert_eq!(
+            hid.mouse_down(1, &mut report),
+            (ReportType::Mouse, [1, 0, 0, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.mouse_move(-1, 2, &mut report),
+            (ReportType::Mouse, [1, 0xFF, 2, 0, 0].as_ref())
+        );
+        assert_eq!(
+            hid.mouse_scroll(120, -120, &mut report),
+            (ReportType::Mouse, [1, 0, 0, 0xFF, 1].as_ref())
+        );
+        assert_eq!(
+            hid.clear(ReportType::Mouse, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_boot_mouse() {
+        let mut hid = super::SynergyHid::new(false);
+        let mut report = [0; MAX_REPORT_SIZE];
+        hid.mouse_down(1, &mut report);
+        // The host reboots into its firmware setup, the held button carries over
+        hid.set_mouse_protocol(Protocol::Boot);
+        assert!(hid.relative_pointer());
+        assert_eq!(
+            hid.mouse_move(5, -5, &mut report),
+            (ReportType::Mouse, [1, 5, 0xFB].as_ref())
+        );
+        assert_eq!(
+            hid.mouse_up(1, &mut report),
+            (ReportType::Mouse, [0, 0, 0].as_ref())
+        );
+        hid.set_mouse_protocol(Protocol::Report);
+        assert!(!hid.relative_pointer());
+        assert_eq!(
+            hid.clear(ReportType::Mouse, &mut report),
+            (ReportType::Mouse, [0, 0, 0, 0, 0, 0, 0].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_split_move() {
+        assert_eq!(split_move(0, 0).next(), None);
+        assert_eq!(
+            split_move(300, -5).collect::<Vec<_>>(),
+            [(127, -5), (127, 0), (46, 0)]
+        );
+        assert_eq!(
+            split_move(-128, 127).collect::<Vec<_>>(),
+            [(-127, 127), (-1, 0)]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use thiserror::Error;
+
+/**
+ * Platform of the server, which decides what the `button` of its key events holds. Mapping the
+ * button types the key at the same place on the host whatever character the server's layout
+ * gives it, the host's own layout then reads it.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScanCodes {
+    // Set 1 scan codes, 0x100 is added to the extended ones
+    Windows,
+    // X11 key codes, evdev key codes + 8 on Linux
+    X11,
+    // Virtual key codes + 1
+    MacOs,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("unknown scan codes")]
+pub struct UnknownScanCodes;
+
+impl ScanCodes {
+    pub const ALL: [ScanCodes; 3] = [ScanCodes::Windows, ScanCodes::X11, ScanCodes::MacOs];
+
+    // Name used by `from_str`, the `SCAN_CODES` build option of the firmware
+    pub const fn name(self) -> &'static str {
+        match self {
+            ScanCodes::Windows => "windows",
+            ScanCodes::X11 => "x11",
+            ScanCodes::MacOs => "macos",
+        }
+    }
+
+    // HID key of the button, None for the buttons without one
+    pub fn hid_key(self, button: u16) -> Option<u8> {
+        let key = match self {
+            ScanCodes::Windows if button & 0x100 != 0 => windows_extended(button & 0xFF),
+            ScanCodes::Windows => table(&WINDOWS_SET_1, button),
+            ScanCodes::X11 => match button.checked_sub(8)? {
+                // F13 - F24
+                code @ 183..=194 => 0x68 + (code - 183) as u8,
+                code => table(&EVDEV, code),
+            },
+            ScanCodes::MacOs => table(&MACOS_VIRTUAL, button.checked_sub(1)?),
+        };
+        Some(key).filter(|&key| key != 0)
+    }
+}
+
+impl core::str::FromStr for ScanCodes {
+    type Err = UnknownScanCodes;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScanCodes::ALL
+            .into_iter()
+            .find(|scan_codes| scan_codes.name().eq_ignore_ascii_case(s))
+            .ok_or(UnknownScanCodes)
+    }
+}
+
+fn table(table: &[u8; 128], code: u16) -> u8 {
+    table.get(code as usize).copied().unwrap_or(0)
+}
+
+// Extended Windows scan codes, the ones sent with an 0xE0 prefix
+fn windows_extended(code: u16) -> u8 {
+    match code {
+        0x1C => 0x58, // Keypad Enter
+        0x1D => 0xE4, // Right Control
+        0x20 => 0x7F, // Mute
+        0x2E => 0x81, // Volume Down
+        0x30 => 0x80, // Volume Up
+        0x35 => 0x54, // Keypad /
+        0x37 => 0x46, // Print Screen
+        0x38 => 0xE6, // Right Alt
+        0x45 => 0x53, // Num Lock, Pause is 0x45 without the prefix
+        0x46 => 0x48, // Break
+        0x47 => 0x4A, // Home
+        0x48 => 0x52, // Up
+        0x49 => 0x4B, // Page Up
+        0x4B => 0x50, // Left
+        0x4D => 0x4F, // Right
+        0x4F => 0x4D, // End
+        0x50 => 0x51, // Down
+        0x51 => 0x4E, // Page Down
+        0x52 => 0x49, // Insert
+        0x53 => 0x4C, // Delete
+        0x5B => 0xE3, // Left GUI
+        0x5C => 0xE7, // Right GUI
+        0x5D => 0x65, // Application
+        0x5E => 0x66, // Power
+        _ => 0,
+    }
+}
+
+// Windows scan codes (set 1) 0x00 - 0x7F, the extended ones are in `windows_extended`
+#[rustfmt::skip]
+const WINDOWS_SET_1: [u8; 128] = [
+    0x00, 0x29, 0x1E, 0x1F, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x2D, 0x2E, 0x2A, 0x2B,
+    0x14, 0x1A, 0x08, 0x15, 0x17, 0x1C, 0x18, 0x0C, 0x12, 0x13, 0x2F, 0x30, 0x28, 0xE0, 0x04, 0x16,
+    0x07, 0x09, 0x0A, 0x0B, 0x0D, 0x0E, 0x0F, 0x33, 0x34, 0x35, 0xE1, 0x31, 0x1D, 0x1B, 0x06, 0x19,
+    0x05, 0x11, 0x10, 0x36, 0x37, 0x38, 0xE5, 0x55, 0xE2, 0x2C, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E,
+    0x3F, 0x40, 0x41, 0x42, 0x43, 0x48, 0x47, 0x5F, 0x60, 0x61, 0x56, 0x5C, 0x5D, 0x5E, 0x57, 0x59,
+    0x5A, 0x5B, 0x62, 0x63, 0x46, 0x00, 0x64, 0x44, 0x45, 0x67, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x68, 0x69, 0x6A, 0x6B, 0x6C, 0x6D, 0x6E, 0x6F, 0x70, 0x71, 0x72, 0x00,
+    0x88, 0x00, 0x00, 0x87, 0x00, 0x00, 0x73, 0x00, 0x00, 0x8A, 0x00, 0x8B, 0x00, 0x89, 0x85, 0x00,
+];
+
+// Linux evdev key codes 0 - 127, X11 key codes are 8 more
+#[rustfmt::skip]
+const EVDEV: [u8; 128] = [
+    0x00, 0x29, 0x1E, 0x1F, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x2D, 0x2E, 0x2A, 0x2B,
+    0x14, 0x1A, 0x08, 0x15, 0x17, 0x1C, 0x18, 0x0C, 0x12, 0x13, 0x2F, 0x30, 0x28, 0xE0, 0x04, 0x16,
+    0x07, 0x09, 0x0A, 0x0B, 0x0D, 0x0E, 0x0F, 0x33, 0x34, 0x35, 0xE1, 0x31, 0x1D, 0x1B, 0x06, 0x19,
+    0x05, 0x11, 0x10, 0x36, 0x37, 0x38, 0xE5, 0x55, 0xE2, 0x2C, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E,
+    0x3F, 0x40, 0x41, 0x42, 0x43, 0x53, 0x47, 0x5F, 0x60, 0x61, 0x56, 0x5C, 0x5D, 0x5E, 0x57, 0x59,
+    0x5A, 0x5B, 0x62, 0x63, 0x00, 0x94, 0x64, 0x44, 0x45, 0x87, 0x92, 0x93, 0x8A, 0x88, 0x8B, 0x8C,
+    0x58, 0xE4, 0x54, 0x46, 0xE6, 0x00, 0x4A, 0x52, 0x4B, 0x50, 0x4F, 0x4D, 0x51, 0x4E, 0x49, 0x4C,
+    0x00, 0x7F, 0x81, 0x80, 0x66, 0x67, 0x00, 0x48, 0x00, 0x85, 0x90, 0x91, 0x89, 0xE3, 0xE7, 0x65,
+];
+
+// macOS virtual key codes 0x00 - 0x7F
+#[rustfmt::skip]
+const MACOS_VIRTUAL: [u8; 128] = [
+    0x04, 0x16, 0x07, 0x09, 0x0B, 0x0A, 0x1D, 0x1B, 0x06, 0x19, 0x64, 0x05, 0x14, 0x1A, 0x08, 0x15,
+    0x1C, 0x17, 0x1E, 0x1F, 0x20, 0x21, 0x23, 0x22, 0x2E, 0x26, 0x24, 0x2D, 0x25, 0x27, 0x30, 0x12,
+    0x18, 0x2F, 0x0C, 0x13, 0x28, 0x0F, 0x0D, 0x34, 0x0E, 0x33, 0x31, 0x36, 0x38, 0x11, 0x10, 0x37,
+    0x2B, 0x2C, 0x35, 0x2A, 0x00, 0x29, 0xE7, 0xE3, 0xE1, 0x39, 0xE2, 0xE0, 0xE5, 0xE6, 0xE4, 0x00,
+    0x6C, 0x63, 0x00, 0x55, 0x00, 0x57, 0x00, 0x53, 0x80, 0x81, 0x7F, 0x54, 0x58, 0x00, 0x56, 0x6D,
+    0x6E, 0x67, 0x62, 0x59, 0x5A, 0x5B, 0x5C, 0x5D, 0x5E, 0x5F, 0x6F, 0x60, 0x61, 0x89, 0x87, 0x85,
+    0x3E, 0x3F, 0x40, 0x3C, 0x41, 0x42, 0x91, 0x44, 0x90, 0x68, 0x6B, 0x69, 0x00, 0x43, 0x65, 0x45,
+    0x00, 0x6A, 0x49, 0x4A, 0x4B, 0x4C, 0x3D, 0x4D, 0x3B, 0x4E, 0x3A, 0x50, 0x4F, 0x51, 0x52, 0x00,
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let windows = ScanCodes::Windows;
+        assert_eq!(windows.hid_key(0x10), Some(0x14)); // Q
+        assert_eq!(windows.hid_key(0x1D), Some(0xE0)); // Left Control
+        assert_eq!(windows.hid_key(0x11D), Some(0xE4)); // Right Control
+        assert_eq!(windows.hid_key(0x48), Some(0x60)); // Keypad 8
+        assert_eq!(windows.hid_key(0x148), Some(0x52)); // Up
+        assert_eq!(windows.hid_key(0x45), Some(0x48)); // Pause
+        assert_eq!(windows.hid_key(0x145), Some(0x53)); // Num Lock
+        assert_eq!(windows.hid_key(0x56), Some(0x64)); // ISO key left of Z
+        assert_eq!(windows.hid_key(0x00), None);
+        assert_eq!(windows.hid_key(0x12A), None);
+        assert_eq!(windows.hid_key(0x1FF), None);
+    }
+
+    #[test]
+    fn test_x11() {
+        let x11 = ScanCodes::X11;
+        assert_eq!(x11.hid_key(24), Some(0x14)); // Q
+        assert_eq!(x11.hid_key(37), Some(0xE0)); // Left Control
+        assert_eq!(x11.hid_key(105), Some(0xE4)); // Right Control
+        assert_eq!(x11.hid_key(108), Some(0xE6)); // AltGr
+        assert_eq!(x11.hid_key(111), Some(0x52)); // Up
+        assert_eq!(x11.hid_key(133), Some(0xE3)); // Left Super
+        assert_eq!(x11.hid_key(191), Some(0x68)); // F13
+        assert_eq!(x11.hid_key(202), Some(0x73)); // F24
+        assert_eq!(x11.hid_key(0), None);
+        assert_eq!(x11.hid_key(8), None);
+        assert_eq!(x11.hid_key(300), None);
+    }
+
+    #[test]
+    fn test_macos() {
+        let macos = ScanCodes::MacOs;
+        assert_eq!(macos.hid_key(0x0C + 1), Some(0x14)); // Q
+        assert_eq!(macos.hid_key(0x01 + 1), Some(0x16)); // S
+        assert_eq!(macos.hid_key(0x37 + 1), Some(0xE3)); // Command
+        assert_eq!(macos.hid_key(0x3A + 1), Some(0xE2)); // Option
+        assert_eq!(macos.hid_key(0x7E + 1), Some(0x52)); // Up
+        assert_eq!(macos.hid_key(0x0A + 1), Some(0x64)); // ISO key left of Z
+        assert_eq!(macos.hid_key(0), None);
+        assert_eq!(macos.hid_key(0x3F + 1), None); // Fn isn't a HID key
+        assert_eq!(macos.hid_key(0x200), None);
+    }
+
+    #[test]
+    fn test_keys() {
+        // Letters, digits, the punctuation of the main block, F1 - F12, the navigation keys, the
+        // keypad and the modifiers are on every platform, Macs have no Print Screen, Scroll Lock
+        // and Pause
+        let keys = (0x04..=0x45).chain(0x49..=0x64).chain(0xE0..=0xE7);
+        let keys = keys.filter(|&key| key != 0x32).collect::<Vec<u8>>();
+        for scan_codes in ScanCodes::ALL {
+            let mapped = (0..0x200)
+                .filter_map(|button| scan_codes.hid_key(button))
+                .collect::<Vec<_>>();
+            for key in &keys {
+                assert!(mapped.contains(key), "{scan_codes:?} {key:#04x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        for scan_codes in ScanCodes::ALL {
+            assert_eq!(scan_codes.name().parse(), Ok(scan_codes));
+        }
+        assert_eq!("X11".parse(), Ok(ScanCodes::X11));
+        assert_eq!("evdev".parse::<ScanCodes>(), Err(UnknownScanCodes));
+    }
+}
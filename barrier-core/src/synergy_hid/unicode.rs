@@ -0,0 +1,271 @@
+use thiserror::Error;
+
+use super::keycodes::{
+    HID_KEY_KEYPAD_0, HID_KEY_KEYPAD_1, HID_KEY_KEYPAD_2, HID_KEY_KEYPAD_3, HID_KEY_KEYPAD_4,
+    HID_KEY_KEYPAD_5, HID_KEY_KEYPAD_6, HID_KEY_KEYPAD_7, HID_KEY_KEYPAD_8, HID_KEY_KEYPAD_9,
+    HID_KEY_KEYPAD_ADD, HID_KEY_SPACE,
+};
+use super::layouts::{Layout, MODIFIER_BIT_ALT, MODIFIER_BIT_CONTROL, MODIFIER_BIT_SHIFT};
+
+// Enough for the longest sequence, macOS typing a surrogate pair
+const MAX_STEPS: usize = 20;
+
+const KEYPAD_DIGITS: [u8; 10] = [
+    HID_KEY_KEYPAD_0,
+    HID_KEY_KEYPAD_1,
+    HID_KEY_KEYPAD_2,
+    HID_KEY_KEYPAD_3,
+    HID_KEY_KEYPAD_4,
+    HID_KEY_KEYPAD_5,
+    HID_KEY_KEYPAD_6,
+    HID_KEY_KEYPAD_7,
+    HID_KEY_KEYPAD_8,
+    HID_KEY_KEYPAD_9,
+];
+
+/**
+ * How the host enters a character by its code point, used for the characters the layout has no
+ * key for.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UnicodeInput {
+    // Ctrl+Shift+U, the code in hex and Space, as GTK and IBus take it
+    Linux,
+    // Alt held while typing + on the numpad and the code in hex, needs EnableHexNumpad in the
+    // registry
+    Windows,
+    // Option held while typing the code in hex, with the Unicode Hex Input source selected
+    MacOs,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("unknown unicode input method")]
+pub struct UnknownUnicodeInput;
+
+/**
+ * Keyboard states that type a character with a `UnicodeInput`, each one is a key and the modifier
+ * bits held with it. Everything is released in the last one.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct UnicodeSteps {
+    steps: [(u8, u8); MAX_STEPS],
+    len: usize,
+    pos: usize,
+}
+
+impl UnicodeSteps {
+    fn push(&mut self, key: u8, modifier: u8) {
+        self.steps[self.len] = (key, modifier);
+        self.len += 1;
+    }
+
+    // Down and up again, `held` stays down around it
+    fn tap(&mut self, (key, modifier): (u8, u8), held: u8) {
+        self.push(key, held | modifier);
+        self.push(0, held);
+    }
+}
+
+impl Iterator for UnicodeSteps {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = self.steps[..self.len].get(self.pos).copied()?;
+        self.pos += 1;
+        Some(step)
+    }
+}
+
+impl UnicodeInput {
+    pub const ALL: [UnicodeInput; 3] = [
+        UnicodeInput::Linux,
+        UnicodeInput::Windows,
+        UnicodeInput::MacOs,
+    ];
+
+    // Name used by `from_str`, the `UNICODE_INPUT` build option of the firmware
+    pub const fn name(self) -> &'static str {
+        match self {
+            UnicodeInput::Linux => "linux",
+            UnicodeInput::Windows => "windows",
+            UnicodeInput::MacOs => "macos",
+        }
+    }
+
+    /**
+     * Keyboard states that enter the character, None if the layout can't type its hex digits or
+     * the input method can't take the character.
+     * Unicode Hex Input is a layout of its own on macOS, the digits are typed as on US there.
+     */
+    pub fn steps(self, layout: Layout, c: char) -> Option<UnicodeSteps> {
+        let mut steps = UnicodeSteps::default();
+        match self {
+            UnicodeInput::Linux => {
+                let held = MODIFIER_BIT_CONTROL | MODIFIER_BIT_SHIFT;
+                steps.tap(layout.lookup('u')?, held);
+                steps.push(0, 0);
+                for digit in hex_digits(c as u32) {
+                    steps.tap(layout.lookup(digit)?, 0);
+                }
+                steps.tap((HID_KEY_SPACE, 0), 0);
+            }
+            UnicodeInput::Windows => {
+                // Hex numpad input stops at the Basic Multilingual Plane
+                if c as u32 > 0xFFFF {
+                    return None;
+                }
+                steps.push(0, MODIFIER_BIT_ALT);
+                steps.tap((HID_KEY_KEYPAD_ADD, 0), MODIFIER_BIT_ALT);
+                for digit in hex_digits(c as u32) {
+                    let key = match digit.to_digit(10) {
+                        Some(d) => (KEYPAD_DIGITS[d as usize], 0),
+                        None => layout.lookup(digit)?,
+                    };
+                    steps.tap(key, MODIFIER_BIT_ALT);
+                }
+                steps.push(0, 0);
+            }
+            UnicodeInput::MacOs => {
+                steps.push(0, MODIFIER_BIT_ALT);
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    for digit in hex_digits(*unit as u32) {
+                        steps.tap(Layout::Us.lookup(digit)?, MODIFIER_BIT_ALT);
+                    }
+                }
+                steps.push(0, 0);
+            }
+        }
+        Some(steps)
+    }
+}
+
+impl core::str::FromStr for UnicodeInput {
+    type Err = UnknownUnicodeInput;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UnicodeInput::ALL
+            .into_iter()
+            .find(|input| input.name().eq_ignore_ascii_case(s))
+            .ok_or(UnknownUnicodeInput)
+    }
+}
+
+// Lowercase hex digits of the code, at least four as macOS wants them
+fn hex_digits(code: u32) -> impl Iterator<Item = char> {
+    let len = (8 - code.leading_zeros() as usize / 4).max(4);
+    (0..len)
+        .rev()
+        .filter_map(move |i| char::from_digit((code >> (i * 4)) & 0xF, 16))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::synergy_hid::keycodes::{
+        HID_KEY_0, HID_KEY_2, HID_KEY_3, HID_KEY_8, HID_KEY_9, HID_KEY_A, HID_KEY_C, HID_KEY_D,
+        HID_KEY_E, HID_KEY_Q, HID_KEY_U,
+    };
+
+    #[test]
+    fn test_hex_digits() {
+        assert_eq!(hex_digits(0xE9).collect::<String>(), "00e9");
+        assert_eq!(hex_digits(0x20AC).collect::<String>(), "20ac");
+        assert_eq!(hex_digits(0x1F600).collect::<String>(), "1f600");
+    }
+
+    #[test]
+    fn test_linux() {
+        let steps = UnicodeInput::Linux.steps(Layout::Us, 'é').unwrap();
+        assert_eq!(
+            steps.collect::<Vec<_>>(),
+            [
+                (HID_KEY_U, 0x03),
+                (0, 0x03),
+                (0, 0),
+                (HID_KEY_0, 0),
+                (0, 0),
+                (HID_KEY_0, 0),
+                (0, 0),
+                (HID_KEY_E, 0),
+                (0, 0),
+                (HID_KEY_9, 0),
+                (0, 0),
+                (HID_KEY_SPACE, 0),
+                (0, 0),
+            ]
+        );
+        // The digits need Shift on AZERTY
+        let steps = UnicodeInput::Linux.steps(Layout::Fr, '€').unwrap();
+        assert_eq!(
+            steps.skip(3).step_by(2).collect::<Vec<_>>(),
+            [
+                (HID_KEY_2, 0x02),
+                (HID_KEY_0, 0x02),
+                (HID_KEY_Q, 0),
+                (HID_KEY_C, 0),
+                (HID_KEY_SPACE, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windows() {
+        let steps = UnicodeInput::Windows.steps(Layout::Us, '€').unwrap();
+        assert_eq!(
+            steps.collect::<Vec<_>>(),
+            [
+                (0, 0x04),
+                (HID_KEY_KEYPAD_ADD, 0x04),
+                (0, 0x04),
+                (HID_KEY_KEYPAD_2, 0x04),
+                (0, 0x04),
+                (HID_KEY_KEYPAD_0, 0x04),
+                (0, 0x04),
+                (HID_KEY_A, 0x04),
+                (0, 0x04),
+                (HID_KEY_C, 0x04),
+                (0, 0x04),
+                (0, 0),
+            ]
+        );
+        assert!(UnicodeInput::Windows
+            .steps(Layout::Us, '\u{FFFD}')
+            .is_some());
+        assert!(UnicodeInput::Windows.steps(Layout::Us, '😀').is_none());
+    }
+
+    #[test]
+    fn test_macos() {
+        // Surrogate pairs are typed one after the other, Option stays down
+        let steps = UnicodeInput::MacOs.steps(Layout::De, '😀').unwrap();
+        let steps = steps.collect::<Vec<_>>();
+        assert_eq!(steps.len(), 18);
+        assert_eq!(steps[0], (0, 0x04));
+        assert_eq!(
+            steps[1..17].iter().step_by(2).collect::<Vec<_>>(),
+            [
+                &(HID_KEY_D, 0x04),
+                &(HID_KEY_8, 0x04),
+                &(HID_KEY_3, 0x04),
+                &(HID_KEY_D, 0x04),
+                &(HID_KEY_D, 0x04),
+                &(HID_KEY_E, 0x04),
+                &(HID_KEY_0, 0x04),
+                &(HID_KEY_0, 0x04),
+            ]
+        );
+        assert_eq!(steps[17], (0, 0));
+        assert!(steps[2..17].iter().step_by(2).all(|&s| s == (0, 0x04)));
+    }
+
+    #[test]
+    fn test_from_str() {
+        for input in UnicodeInput::ALL {
+            assert_eq!(input.name().parse(), Ok(input));
+        }
+        assert_eq!("macOS".parse(), Ok(UnicodeInput::MacOs));
+        assert_eq!("bsd".parse::<UnicodeInput>(), Err(UnknownUnicodeInput));
+    }
+}
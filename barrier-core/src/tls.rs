@@ -0,0 +1,261 @@
+use rsa::{pkcs8::DecodePublicKey, pss, signature::Verifier, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CertificateError {
+    #[error("certificate doesn't match the pinned fingerprint")]
+    FingerprintMismatch,
+    #[error("malformed certificate")]
+    Malformed,
+    #[error("certificate doesn't have an RSA key")]
+    NotRsa,
+    #[error("CertificateVerify signature is invalid")]
+    InvalidSignature,
+}
+
+/**
+ * Hash of the RSA-PSS signature scheme the server signs CertificateVerify with.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PssHash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+/**
+ * Parses a SHA-256 fingerprint as shown by Barrier, hex digits optionally separated by colons.
+ */
+pub fn parse_fingerprint(s: &str) -> Option<[u8; 32]> {
+    let mut fingerprint = [0u8; 32];
+    let mut digits = s.chars().filter(|c| *c != ':');
+    for byte in fingerprint.iter_mut() {
+        let hi = digits.next()?.to_digit(16)?;
+        let lo = digits.next()?.to_digit(16)?;
+        *byte = (hi << 4 | lo) as u8;
+    }
+    if digits.next().is_some() {
+        return None;
+    }
+    Some(fingerprint)
+}
+
+/**
+ * SHA-256 fingerprint of a DER certificate, as `parse_fingerprint` returns it.
+ */
+pub fn fingerprint(cert: &[u8]) -> [u8; 32] {
+    Sha256::digest(cert).into()
+}
+
+/**
+ * RSA key of a server certificate that matched the pinned fingerprint.
+ * Barrier generates self-signed RSA certificates, so there is no CA to check against, and the
+ * CertificateVerify signature proves the server holds the private key of the certificate.
+ */
+pub struct PinnedKey(RsaPublicKey);
+
+impl PinnedKey {
+    pub fn from_certificate(cert: &[u8], pinned: &[u8; 32]) -> Result<Self, CertificateError> {
+        if fingerprint(cert) != *pinned {
+            return Err(CertificateError::FingerprintMismatch);
+        }
+        let spki = subject_public_key_info(cert).ok_or(CertificateError::Malformed)?;
+        let key = RsaPublicKey::from_public_key_der(spki).map_err(|_| CertificateError::NotRsa)?;
+        Ok(Self(key))
+    }
+
+    /**
+     * Checks the CertificateVerify signature over the handshake transcript hash.
+     */
+    pub fn verify(
+        self,
+        hash: PssHash,
+        transcript_hash: &[u8],
+        signature: &[u8],
+    ) -> Result<(), CertificateError> {
+        // RFC 8446 4.4.3, 64 spaces, the context string and the transcript hash
+        const CONTEXT: &[u8] = b"TLS 1.3, server CertificateVerify\x00";
+        if transcript_hash.len() > 64 {
+            return Err(CertificateError::InvalidSignature);
+        }
+        let mut message = [0x20u8; 64 + CONTEXT.len() + 64];
+        message[64..64 + CONTEXT.len()].copy_from_slice(CONTEXT);
+        message[64 + CONTEXT.len()..64 + CONTEXT.len() + transcript_hash.len()]
+            .copy_from_slice(transcript_hash);
+        let message = &message[..64 + CONTEXT.len() + transcript_hash.len()];
+
+        let signature =
+            pss::Signature::try_from(signature).map_err(|_| CertificateError::InvalidSignature)?;
+        let ret = match hash {
+            PssHash::Sha256 => pss::VerifyingKey::<Sha256>::new(self.0).verify(message, &signature),
+            PssHash::Sha384 => pss::VerifyingKey::<Sha384>::new(self.0).verify(message, &signature),
+            PssHash::Sha512 => pss::VerifyingKey::<Sha512>::new(self.0).verify(message, &signature),
+        };
+        ret.map_err(|_| CertificateError::InvalidSignature)
+    }
+}
+
+// Splits a DER element into (tag, content, rest)
+fn der_next(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first = *data.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let n = first & 0x7f;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let mut len = 0usize;
+        for &b in data.get(2..2 + n)? {
+            len = (len << 8) | b as usize;
+        }
+        (len, 2 + n)
+    };
+    let end = header.checked_add(len)?;
+    Some((tag, data.get(header..end)?, &data[end..]))
+}
+
+/**
+ * Returns the whole SubjectPublicKeyInfo element of an X.509 certificate.
+ * It follows the optional version, serial number, signature algorithm, issuer, validity and subject
+ * in the TBSCertificate.
+ */
+fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    let (_, cert, _) = der_next(cert)?;
+    let (_, tbs, _) = der_next(cert)?;
+    let mut rest = tbs;
+    if rest.first() == Some(&0xA0) {
+        rest = der_next(rest)?.2;
+    }
+    for _ in 0..5 {
+        rest = der_next(rest)?.2;
+    }
+    let (_, _, after) = der_next(rest)?;
+    Some(&rest[..rest.len() - after.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated like Barrier does, see tests/fixtures/Barrier.pem for the key
+    const CERT: &[u8] = include_bytes!("../tests/fixtures/barrier.der");
+    const CERT_FINGERPRINT: &str = "9C:1C:E0:12:DD:54:06:3C:8F:18:AB:8F:9D:97:62:BB:52:A0:B1:80:C0:02:0B:44:5B:B1:26:86:AA:F3:F3:81";
+    const EC_CERT: &[u8] = include_bytes!("../tests/fixtures/barrier_ec.der");
+    // RSA-PSS SHA-256 signature of the CertificateVerify message over the hash of "transcript"
+    const SIGNATURE: &[u8] = include_bytes!("../tests/fixtures/barrier_verify.sig");
+
+    #[test]
+    fn test_parse_fingerprint() {
+        let pinned = parse_fingerprint(CERT_FINGERPRINT).unwrap();
+        assert_eq!(pinned, fingerprint(CERT));
+        let plain = CERT_FINGERPRINT.replace(':', "").to_lowercase();
+        assert_eq!(parse_fingerprint(&plain), Some(pinned));
+
+        assert_eq!(parse_fingerprint(""), None);
+        assert_eq!(
+            parse_fingerprint(&CERT_FINGERPRINT[..CERT_FINGERPRINT.len() - 3]),
+            None
+        );
+        assert_eq!(parse_fingerprint(&format!("{}:00", CERT_FINGERPRINT)), None);
+        assert_eq!(
+            parse_fingerprint(&CERT_FINGERPRINT.replace("9C", "9G")),
+            None
+        );
+        // A SHA-1 fingerprint is too short
+        assert_eq!(parse_fingerprint(&CERT_FINGERPRINT[..59]), None);
+    }
+
+    #[test]
+    fn test_pinned_certificate() {
+        let pinned = parse_fingerprint(CERT_FINGERPRINT).unwrap();
+        assert!(PinnedKey::from_certificate(CERT, &pinned).is_ok());
+
+        let mut wrong = pinned;
+        wrong[31] ^= 1;
+        assert_eq!(
+            PinnedKey::from_certificate(CERT, &wrong).err(),
+            Some(CertificateError::FingerprintMismatch)
+        );
+        assert_eq!(
+            PinnedKey::from_certificate(EC_CERT, &pinned).err(),
+            Some(CertificateError::FingerprintMismatch)
+        );
+    }
+
+    #[test]
+    fn test_non_rsa_certificate() {
+        assert!(subject_public_key_info(EC_CERT).is_some());
+        assert_eq!(
+            PinnedKey::from_certificate(EC_CERT, &fingerprint(EC_CERT)).err(),
+            Some(CertificateError::NotRsa)
+        );
+    }
+
+    #[test]
+    fn test_malformed_certificate() {
+        for len in [0, 1, 4, 100, CERT.len() - 1] {
+            let truncated = &CERT[..len];
+            assert_eq!(
+                PinnedKey::from_certificate(truncated, &fingerprint(truncated)).err(),
+                Some(CertificateError::Malformed),
+                "truncated to {}",
+                len
+            );
+        }
+
+        // The outer SEQUENCE has a two byte length, claim more than there is
+        assert_eq!(CERT[1], 0x82);
+        let mut overlong = CERT.to_vec();
+        overlong[2] = 0xFF;
+        assert_eq!(
+            PinnedKey::from_certificate(&overlong, &fingerprint(&overlong)).err(),
+            Some(CertificateError::Malformed)
+        );
+
+        // Lengths of more than four bytes, indefinite lengths and lengths overflowing usize
+        assert_eq!(der_next(&[0x30, 0x85, 0, 0, 0, 0, 1, 0]), None);
+        assert_eq!(der_next(&[0x30, 0x80, 0, 0]), None);
+        assert_eq!(der_next(&[0x30, 0x84, 0xFF, 0xFF, 0xFF, 0xFF]), None);
+        assert_eq!(der_next(&[0x30, 0x82, 0x01]), None);
+        assert_eq!(
+            der_next(&[0x02, 0x01, 0x05, 0x30]),
+            Some((0x02, &[0x05][..], &[0x30][..]))
+        );
+    }
+
+    #[test]
+    fn test_certificate_verify() {
+        let pinned = parse_fingerprint(CERT_FINGERPRINT).unwrap();
+        let key = || PinnedKey::from_certificate(CERT, &pinned).unwrap();
+        let transcript_hash = Sha256::digest(b"transcript");
+
+        assert_eq!(
+            key().verify(PssHash::Sha256, &transcript_hash, SIGNATURE),
+            Ok(())
+        );
+
+        let other_hash = Sha256::digest(b"other transcript");
+        assert_eq!(
+            key().verify(PssHash::Sha256, &other_hash, SIGNATURE),
+            Err(CertificateError::InvalidSignature)
+        );
+        let mut signature = SIGNATURE.to_vec();
+        signature[100] ^= 1;
+        assert_eq!(
+            key().verify(PssHash::Sha256, &transcript_hash, &signature),
+            Err(CertificateError::InvalidSignature)
+        );
+        assert_eq!(
+            key().verify(PssHash::Sha384, &transcript_hash, SIGNATURE),
+            Err(CertificateError::InvalidSignature)
+        );
+        assert_eq!(
+            key().verify(PssHash::Sha256, &[0; 65], SIGNATURE),
+            Err(CertificateError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use barrier_core::{
+    barrier::{self, Capture, Packet},
+    synergy_hid::ReportType,
+};
+use common::{duplex, recording_actuator, Event, FakeServer, RecordingActuator, Step};
+use futures::{executor::block_on, future::join};
+
+const SCREEN_NAME: &str = "pico";
+
+/**
+ * Runs the script against the client with capture enabled and returns the exported capture.
+ */
+fn capture_session(
+    actor: &mut RecordingActuator,
+    capture_size: usize,
+    script: &[Step],
+) -> (Vec<u8>, usize) {
+    let mut buffer = vec![0; capture_size];
+    let mut capture = Capture::new(&mut buffer);
+    let (client, server) = duplex();
+    let (ret, _) = block_on(join(
+        barrier::start_with_capture(client, SCREEN_NAME, actor, &mut || {}, &mut capture),
+        async {
+            let (mut server, _) = FakeServer::accept(server, "Barrier", 1, 6).await.unwrap();
+            server.run(script).await;
+        },
+    ));
+    assert!(ret.is_err());
+    let mut exported = vec![0; capture.len()];
+    block_on(capture.export(exported.as_mut_slice())).unwrap();
+    (exported, capture.dropped())
+}
+
+fn typing_script() -> Vec<Step> {
+    let mut script = vec![
+        Step::Send(Packet::CursorEnter {
+            x: 100,
+            y: 100,
+            seq_num: 1,
+            mask: 0,
+        }),
+        Step::Send(Packet::QueryInfo),
+        Step::Expect(Packet::DeviceInfo {
+            x: 100,
+            y: 100,
+            w: 1920,
+            h: 1080,
+            _dummy: 0,
+            mx: 0,
+            my: 0,
+        }),
+    ];
+    for c in "hello".chars() {
+        script.push(Step::Send(Packet::KeyDown {
+            id: c as u16,
+            mask: 0,
+            button: c as u16,
+        }));
+        script.push(Step::Send(Packet::KeyUp {
+            id: c as u16,
+            mask: 0,
+            button: c as u16,
+        }));
+    }
+    script.push(Step::Send(Packet::MouseMoveAbs { x: 960, y: 540 }));
+    script.push(Step::Send(Packet::CursorLeave));
+    script
+}
+
+#[test]
+fn test_replay_matches_session() {
+    let mut live = recording_actuator(1920, 1080);
+    let (exported, dropped) = capture_session(&mut live, 4096, &typing_script());
+    assert_eq!(dropped, 0);
+
+    let mut replayed = recording_actuator(1920, 1080);
+    let ret = block_on(barrier::replay(&exported, SCREEN_NAME, &mut replayed));
+    assert!(ret.is_err());
+    assert_eq!(replayed.sink().events, live.sink().events);
+}
+
+#[test]
+fn test_ring_keeps_newest_frames() {
+    let mut live = recording_actuator(1920, 1080);
+    // Room for the last two frames only, 20 bytes for the mouse move and 16 for leaving
+    let (exported, dropped) = capture_session(&mut live, 20 + 16, &typing_script());
+    assert!(dropped > 0);
+
+    let mut replayed = recording_actuator(1920, 1080);
+    block_on(barrier::replay(&exported, SCREEN_NAME, &mut replayed)).ok();
+    // Mouse move and leave, then the release on disconnect
+    let (x, y) = replayed.scale_position(960, 540);
+    let [x, y] = [x, y].map(u16::to_le_bytes);
+    assert_eq!(
+        replayed.sink().reports(ReportType::Mouse),
+        vec![
+            vec![0, x[0], x[1], y[0], y[1], 0, 0],
+            vec![0, x[0], x[1], y[0], y[1], 0, 0],
+            vec![0, x[0], x[1], y[0], y[1], 0, 0],
+        ]
+    );
+    assert!(replayed.sink().events.contains(&Event::Leave));
+}
+
+#[test]
+fn test_truncated_frame_is_skipped() {
+    let mut capture = Vec::new();
+    // A DKDN cut short, then COUT
+    capture.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 8]);
+    capture.extend_from_slice(&[0, 0, 0, 10, b'D', b'K', b'D', b'N']);
+    capture.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 8]);
+    capture.extend_from_slice(&[0, 0, 0, 4, b'C', b'O', b'U', b'T']);
+
+    let mut actor = recording_actuator(1920, 1080);
+    block_on(barrier::replay(&capture, SCREEN_NAME, &mut actor)).ok();
+    assert_eq!(
+        actor.sink().reports(ReportType::Keyboard),
+        vec![vec![0, 0, 0, 0, 0, 0, 0, 0], vec![0, 0, 0, 0, 0, 0, 0, 0]]
+    );
+    assert!(actor.sink().events.contains(&Event::Leave));
+}
+
+// Shift was pressed on the server but its key up never arrived, leaving the screen releases it
+#[test]
+fn test_stuck_shift_fixture() {
+    let mut actor = recording_actuator(1920, 1080);
+    block_on(barrier::replay(
+        include_bytes!("fixtures/stuck_shift.capture"),
+        SCREEN_NAME,
+        &mut actor,
+    ))
+    .ok();
+    assert_eq!(
+        actor.sink().reports(ReportType::Keyboard),
+        vec![
+            vec![0x02, 0, 0, 0, 0, 0, 0, 0],
+            vec![0x02, 0, 0x04, 0, 0, 0, 0, 0],
+            vec![0x02, 0, 0, 0, 0, 0, 0, 0],
+            // Released on leave and on disconnect
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+        ]
+    );
+}
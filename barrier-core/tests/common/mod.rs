@@ -0,0 +1,316 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    future::poll_fn,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use barrier_core::{
+    barrier::{
+        ClipboardFormat, ClipboardMark, Options, Packet, PacketError, PacketReader, PacketStream,
+        PacketWriter,
+    },
+    hid_actuator::{HidActuator, ReportSink},
+    synergy_hid::{KeyboardLeds, Protocol, ReportType, SynergyHid},
+};
+use embedded_io_async::{ErrorType, Read, Write};
+
+#[derive(Default)]
+struct Buffer {
+    data: VecDeque<u8>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/**
+ * One end of an in-memory byte pipe, dropping it closes the connection for the other end.
+ */
+pub struct Endpoint {
+    rx: Arc<Mutex<Buffer>>,
+    tx: Arc<Mutex<Buffer>>,
+}
+
+pub fn duplex() -> (Endpoint, Endpoint) {
+    let a = Arc::new(Mutex::new(Buffer::default()));
+    let b = Arc::new(Mutex::new(Buffer::default()));
+    (
+        Endpoint {
+            rx: a.clone(),
+            tx: b.clone(),
+        },
+        Endpoint { rx: b, tx: a },
+    )
+}
+
+impl ErrorType for Endpoint {
+    type Error = Infallible;
+}
+
+impl Read for Endpoint {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        poll_fn(|cx| {
+            let mut rx = self.rx.lock().unwrap();
+            if rx.data.is_empty() && !rx.closed {
+                rx.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            let len = std::cmp::min(buf.len(), rx.data.len());
+            for (dst, src) in buf.iter_mut().zip(rx.data.drain(..len)) {
+                *dst = src;
+            }
+            Poll::Ready(Ok(len))
+        })
+        .await
+    }
+}
+
+impl Write for Endpoint {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        let mut tx = self.tx.lock().unwrap();
+        tx.data.extend(buf);
+        if let Some(waker) = tx.waker.take() {
+            waker.wake();
+        }
+        Ok(buf.len())
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        let mut tx = self.tx.lock().unwrap();
+        tx.closed = true;
+        if let Some(waker) = tx.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/**
+ * What the client sent back to the greeting.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHello {
+    pub literal: String,
+    pub major: u16,
+    pub minor: u16,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    // Sends a packet to the client
+    Send(Packet<'static>),
+    // Sends a whole clipboard transfer, start, one chunk and end
+    SendClipboard { id: u8, seq_num: u32, text: String },
+    // Reads the next packet from the client and checks it
+    Expect(Packet<'static>),
+}
+
+/**
+ * Server side of a Barrier session driven by a script, it panics as soon as the client doesn't
+ * answer as expected.
+ */
+pub struct FakeServer {
+    stream: Endpoint,
+}
+
+impl FakeServer {
+    /**
+     * Greets the client and reads its reply, the connection is ready for the script afterwards.
+     */
+    pub async fn accept(
+        mut stream: Endpoint,
+        literal: &str,
+        major: u16,
+        minor: u16,
+    ) -> Result<(Self, ClientHello), PacketError> {
+        stream.write_u32(literal.len() as u32 + 2 + 2).await?;
+        stream
+            .write_all(literal.as_bytes())
+            .await
+            .map_err(|_| PacketError::IoError)?;
+        stream.write_u16(major).await?;
+        stream.write_u16(minor).await?;
+
+        let size = stream.read_packet_size().await? as usize;
+        let mut reply = vec![0; size];
+        stream.read_exact(&mut reply).await?;
+        let mut reply = reply.as_slice();
+        let literal: [u8; 7] = reply.read_bytes_fixed().await?;
+        let major = reply.read_u16().await?;
+        let minor = reply.read_u16().await?;
+        let len = reply.read_u32().await? as usize;
+        let name = String::from_utf8(reply.get(..len).ok_or(PacketError::Eof)?.to_vec())
+            .map_err(|_| PacketError::FormatError)?;
+        let hello = ClientHello {
+            literal: String::from_utf8_lossy(&literal).into_owned(),
+            major,
+            minor,
+            name,
+        };
+        Ok((Self { stream }, hello))
+    }
+
+    pub async fn send(&mut self, packet: Packet<'_>) {
+        packet
+            .write_wire(&mut self.stream)
+            .await
+            .expect("Failed to send packet");
+    }
+
+    /**
+     * Sends the text as a plain text clipboard, the way Barrier does when the clipboard is
+     * small enough to fit in one chunk.
+     */
+    pub async fn send_clipboard(&mut self, id: u8, seq_num: u32, text: &str) {
+        let mut data = Vec::new();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&(ClipboardFormat::Text as u32).to_be_bytes());
+        data.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        data.extend_from_slice(text.as_bytes());
+
+        let size = data.len().to_string();
+        let chunks = [
+            (ClipboardMark::Start, size.as_bytes()),
+            (ClipboardMark::Chunk, &data),
+            (ClipboardMark::End, &[]),
+        ];
+        for (mark, data) in chunks {
+            self.send(Packet::ClipboardData {
+                id,
+                seq_num,
+                mark,
+                data,
+            })
+            .await;
+        }
+    }
+
+    pub async fn expect(&mut self, expected: Packet<'_>) {
+        let mut stream = PacketStream::new(&mut self.stream);
+        let packet = stream.read().await.expect("Client didn't reply");
+        assert_eq!(packet, expected);
+    }
+
+    pub async fn run(&mut self, script: &[Step]) {
+        for step in script {
+            match step {
+                Step::Send(packet) => self.send(packet.clone()).await,
+                Step::SendClipboard { id, seq_num, text } => {
+                    self.send_clipboard(*id, *seq_num, text).await
+                }
+                Step::Expect(packet) => self.expect(packet.clone()).await,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Connected,
+    Disconnected,
+    Report(ReportType, Vec<u8>),
+    Clipboard(u8, String),
+    Options(Options),
+    ResetOptions,
+    Enter,
+    Leave,
+}
+
+/**
+ * Records the reports `HidActuator` makes and the other events instead of sending them to a host.
+ */
+pub struct Recorder {
+    // Keyboard LEDs of the pretend host
+    pub leds: KeyboardLeds,
+    // Protocols the pretend host picked for the keyboard and mouse interfaces
+    pub keyboard_protocol: Protocol,
+    pub mouse_protocol: Protocol,
+    // Resolution Multiplier feature report the pretend host set
+    pub resolution_multiplier: u8,
+    pub events: Vec<Event>,
+}
+
+impl Recorder {
+    /**
+     * All reports of the given type, in the order they were produced.
+     */
+    pub fn reports(&self, report_type: ReportType) -> Vec<Vec<u8>> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Report(t, r) if *t == report_type => Some(r.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl ReportSink for Recorder {
+    async fn send_report(&mut self, report: (ReportType, &[u8])) {
+        self.events.push(Event::Report(report.0, report.1.to_vec()));
+    }
+
+    fn keyboard_protocol(&self) -> Protocol {
+        self.keyboard_protocol
+    }
+
+    fn mouse_protocol(&self) -> Protocol {
+        self.mouse_protocol
+    }
+
+    fn resolution_multiplier(&self) -> u8 {
+        self.resolution_multiplier
+    }
+
+    fn keyboard_leds(&self) -> KeyboardLeds {
+        self.leds
+    }
+
+    async fn connected(&mut self) {
+        self.events.push(Event::Connected);
+    }
+
+    async fn disconnected(&mut self) {
+        self.events.push(Event::Disconnected);
+    }
+
+    async fn set_clipboard(&mut self, id: u8, text: &str) {
+        self.events.push(Event::Clipboard(id, text.to_string()));
+    }
+
+    async fn set_options(&mut self, options: Options) {
+        self.events.push(Event::Options(options));
+    }
+
+    async fn reset_options(&mut self) {
+        self.events.push(Event::ResetOptions);
+    }
+
+    async fn enter(&mut self, _x: u16, _y: u16, _mask: u16) {
+        self.events.push(Event::Enter);
+    }
+
+    async fn leave(&mut self) {
+        self.events.push(Event::Leave);
+    }
+}
+
+/**
+ * The actuator of the firmware with a recorder in place of the USB host.
+ */
+pub type RecordingActuator = HidActuator<Recorder>;
+
+pub fn recording_actuator(width: u16, height: u16) -> RecordingActuator {
+    let recorder = Recorder {
+        leds: KeyboardLeds::default(),
+        keyboard_protocol: Protocol::Report,
+        mouse_protocol: Protocol::Report,
+        resolution_multiplier: 0,
+        events: Vec::new(),
+    };
+    HidActuator::new(width, height, SynergyHid::new(false), recorder)
+}
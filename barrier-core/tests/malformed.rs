@@ -0,0 +1,259 @@
+//! Inputs found by the fuzz targets in `fuzz/`, reduced by hand.
+
+mod common;
+
+use barrier_core::{
+    barrier::{
+        self, ConnectionError, OptionList, Packet, PacketError, PacketReader, PacketStream,
+        OPTION_HALF_DUPLEX_NUM_LOCK, OPTION_HEARTBEAT,
+    },
+    synergy_hid::ReportType,
+};
+use common::{duplex, recording_actuator, Event, RecordingActuator};
+use embedded_io_async::Write;
+use futures::executor::block_on;
+
+const HELLO: &[u8] = b"\x00\x00\x00\x0bBarrier\x00\x01\x00\x06";
+const CLOSE: &[u8] = b"\x00\x00\x00\x04CBYE";
+
+/**
+ * Reads what the server sent until the stream ends, malformed packets don't stop the reading.
+ */
+fn read_all(bytes: &[u8]) -> Vec<Result<Packet<'static>, PacketError>> {
+    let (client, mut server) = duplex();
+    block_on(server.write_all(bytes)).unwrap();
+    drop(server);
+    let mut stream = PacketStream::new(client);
+    let mut packets = Vec::new();
+    loop {
+        let ret = block_on(stream.read()).map(owned);
+        let done = matches!(ret, Err(PacketError::Eof | PacketError::IoError));
+        packets.push(ret);
+        if done {
+            return packets;
+        }
+    }
+}
+
+/**
+ * Copies the packet out of the stream it borrows its clipboard data from, leaking the data.
+ */
+fn owned(packet: Packet<'_>) -> Packet<'static> {
+    match packet {
+        Packet::QueryInfo => Packet::QueryInfo,
+        Packet::DeviceInfo {
+            x,
+            y,
+            w,
+            h,
+            _dummy,
+            mx,
+            my,
+        } => Packet::DeviceInfo {
+            x,
+            y,
+            w,
+            h,
+            _dummy,
+            mx,
+            my,
+        },
+        Packet::InfoAck => Packet::InfoAck,
+        Packet::KeepAlive => Packet::KeepAlive,
+        Packet::ResetOptions => Packet::ResetOptions,
+        Packet::SetOptions(options) => Packet::SetOptions(options),
+        Packet::ClientNoOp => Packet::ClientNoOp,
+        Packet::ErrorUnknownDevice => Packet::ErrorUnknownDevice,
+        Packet::ErrorIncompatible { major, minor } => Packet::ErrorIncompatible { major, minor },
+        Packet::ErrorBusy => Packet::ErrorBusy,
+        Packet::ErrorBadMessage => Packet::ErrorBadMessage,
+        Packet::Close => Packet::Close,
+        Packet::CursorEnter {
+            x,
+            y,
+            seq_num,
+            mask,
+        } => Packet::CursorEnter {
+            x,
+            y,
+            seq_num,
+            mask,
+        },
+        Packet::MouseUp { id } => Packet::MouseUp { id },
+        Packet::MouseDown { id } => Packet::MouseDown { id },
+        Packet::KeyUp { id, mask, button } => Packet::KeyUp { id, mask, button },
+        Packet::KeyDown { id, mask, button } => Packet::KeyDown { id, mask, button },
+        Packet::KeyRepeat {
+            id,
+            mask,
+            button,
+            count,
+        } => Packet::KeyRepeat {
+            id,
+            mask,
+            button,
+            count,
+        },
+        Packet::MouseWheel { x_delta, y_delta } => Packet::MouseWheel { x_delta, y_delta },
+        Packet::CursorLeave => Packet::CursorLeave,
+        Packet::MouseMoveAbs { x, y } => Packet::MouseMoveAbs { x, y },
+        Packet::MouseMove { x, y } => Packet::MouseMove { x, y },
+        Packet::GrabClipboard { id, seq_num } => Packet::GrabClipboard { id, seq_num },
+        Packet::ClipboardData {
+            id,
+            seq_num,
+            mark,
+            data,
+        } => Packet::ClipboardData {
+            id,
+            seq_num,
+            mark,
+            data: data.to_vec().leak(),
+        },
+        Packet::Unknown(code) => Packet::Unknown(code),
+    }
+}
+
+fn connect(bytes: &[u8]) -> (Result<(), ConnectionError>, RecordingActuator) {
+    let mut actor = recording_actuator(1920, 1080);
+    let (client, mut server) = duplex();
+    block_on(server.write_all(bytes)).unwrap();
+    drop(server);
+    let ret = block_on(barrier::start(client, "pico", &mut actor, &mut || {}));
+    (ret, actor)
+}
+
+// A DKDN without the button, the next packet is still read from the right place
+#[test]
+fn test_fields_past_packet_size() {
+    let packets = read_all(b"\x00\x00\x00\x08DKDN\x00\x61\x00\x00\x00\x00\x00\x04COUT");
+    assert!(
+        matches!(
+            packets.as_slice(),
+            [
+                Err(PacketError::PacketTooSmall),
+                Ok(Packet::CursorLeave),
+                Err(PacketError::Eof)
+            ]
+        ),
+        "{:?}",
+        packets
+    );
+}
+
+// The clipboard chunk says 100 bytes, the packet only has 2
+#[test]
+fn test_clipboard_chunk_past_packet_size() {
+    let packets = read_all(b"\x00\x00\x00\x10DCLP\x00\x00\x00\x00\x01\x02\x00\x00\x00\x64hi");
+    assert!(
+        matches!(
+            packets.as_slice(),
+            [Err(PacketError::PacketTooSmall), Err(PacketError::Eof)]
+        ),
+        "{:?}",
+        packets
+    );
+}
+
+#[test]
+fn test_options_past_packet_size() {
+    let packets = read_all(b"\x00\x00\x00\x10DSOP\xff\xff\xff\xfeHBRT\x00\x00\x13\x88");
+    assert!(
+        matches!(
+            packets.as_slice(),
+            [Err(PacketError::PacketTooSmall), Err(PacketError::Eof)]
+        ),
+        "{:?}",
+        packets
+    );
+}
+
+// More options than the list holds, the ones the client knows come last
+#[test]
+fn test_too_many_options() {
+    let mut body = b"DSOP\x00\x00\x00\x2c".to_vec();
+    for i in 0..20u8 {
+        body.extend_from_slice(&[b'X', b'X', b'0', i, 0, 0, 0, 1]);
+    }
+    body.extend_from_slice(b"HART\x00\x00\x13\x88HDNL\x00\x00\x00\x01");
+    let mut bytes = (body.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(&body);
+
+    let mut expected = OptionList::default();
+    expected.push(OPTION_HEARTBEAT, 5000);
+    expected.push(OPTION_HALF_DUPLEX_NUM_LOCK, 1);
+    let packets = read_all(&bytes);
+    assert!(
+        matches!(
+            packets.as_slice(),
+            [Ok(Packet::SetOptions(options)), Err(PacketError::Eof)] if *options == expected
+        ),
+        "{:?}",
+        packets
+    );
+}
+
+// The connection closes in the middle of a packet, discarding the rest of it used to spin forever
+#[test]
+fn test_stream_ends_inside_packet() {
+    let packets = read_all(b"\x00\x00\x00\x05\x00\x00\x00\x81");
+    assert!(
+        matches!(packets.as_slice(), [Err(PacketError::Eof)]),
+        "{:?}",
+        packets
+    );
+}
+
+// Extra bytes after the version belong to the greeting, not to the next packet
+#[test]
+fn test_hello_with_trailing_bytes() {
+    let mut bytes = b"\x00\x00\x00\x0fBarrier\x00\x01\x00\x06\x00\x00\x00\x04".to_vec();
+    bytes.extend_from_slice(CLOSE);
+    let (ret, actor) = connect(&bytes);
+    assert!(ret.is_ok());
+    assert_eq!(actor.sink().events.first(), Some(&Event::Connected));
+}
+
+#[test]
+fn test_hello_too_short() {
+    let mut bytes = b"\x00\x00\x00\x04".to_vec();
+    bytes.extend_from_slice(&HELLO[4..]);
+    let (ret, actor) = connect(&bytes);
+    assert!(matches!(
+        ret,
+        Err(ConnectionError::ProtocolError(PacketError::PacketTooSmall))
+    ));
+    assert!(actor.sink().events.is_empty());
+}
+
+// The button indexes the table of pressed keys
+#[test]
+fn test_key_button_out_of_range() {
+    let mut bytes = HELLO.to_vec();
+    bytes.extend_from_slice(b"\x00\x00\x00\x0aDKDN\x00\x61\x00\x00\xff\xff");
+    bytes.extend_from_slice(b"\x00\x00\x00\x0aDKUP\x00\x61\x00\x00\xff\xff");
+    bytes.extend_from_slice(CLOSE);
+    let (ret, actor) = connect(&bytes);
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().events[1..3],
+        [
+            Event::Report(ReportType::Keyboard, vec![0, 0, 0x04, 0, 0, 0, 0, 0]),
+            Event::Report(ReportType::Keyboard, vec![0; 8]),
+        ]
+    );
+}
+
+#[test]
+fn test_long_literal() {
+    let lit = "A literal longer than sixteen bytes";
+    let mut bytes = lit.as_bytes();
+    assert!(block_on(bytes.read_str_lit(lit)).is_ok());
+    assert!(bytes.is_empty());
+
+    let mut bytes = "A literal longer than sixteen bites".as_bytes();
+    assert!(matches!(
+        block_on(bytes.read_str_lit(lit)),
+        Err(PacketError::FormatError)
+    ));
+}
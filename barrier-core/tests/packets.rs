@@ -0,0 +1,117 @@
+//! Packets written with `Packet::write_wire` read back the same through `PacketStream`.
+
+mod common;
+
+use barrier_core::barrier::{
+    ClipboardFormat, ClipboardMark, OptionList, Packet, PacketError, PacketStream, OPTION_HEARTBEAT,
+};
+use common::duplex;
+use futures::executor::block_on;
+
+/**
+ * Writes the packets, checks that they read back the same and returns the clipboard text the
+ * stream put together.
+ */
+fn round_trip(packets: &[Packet]) -> Option<String> {
+    let (client, mut server) = duplex();
+    for packet in packets {
+        block_on(packet.clone().write_wire(&mut server)).unwrap();
+    }
+    drop(server);
+    let mut stream = PacketStream::new(client);
+    for packet in packets {
+        match block_on(stream.read()) {
+            Ok(read) => assert_eq!(read, *packet),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+    assert!(matches!(block_on(stream.read()), Err(PacketError::Eof)));
+    stream.clipboard().text().map(String::from)
+}
+
+#[test]
+fn test_round_trip() {
+    let mut options = OptionList::default();
+    options.push(OPTION_HEARTBEAT, 5000);
+    let packets = [
+        Packet::QueryInfo,
+        Packet::DeviceInfo {
+            x: 0,
+            y: 0,
+            w: 1920,
+            h: 1080,
+            _dummy: 0,
+            mx: 960,
+            my: 540,
+        },
+        Packet::InfoAck,
+        Packet::KeepAlive,
+        Packet::ResetOptions,
+        Packet::SetOptions(options),
+        Packet::ClientNoOp,
+        Packet::ErrorUnknownDevice,
+        Packet::ErrorIncompatible { major: 1, minor: 6 },
+        Packet::ErrorBusy,
+        Packet::ErrorBadMessage,
+        Packet::CursorEnter {
+            x: 10,
+            y: 20,
+            seq_num: 3,
+            mask: 0x1000,
+        },
+        Packet::MouseDown { id: 1 },
+        Packet::MouseUp { id: 1 },
+        Packet::KeyDown {
+            id: 'a' as u16,
+            mask: 0x0001,
+            button: 38,
+        },
+        Packet::KeyRepeat {
+            id: 'a' as u16,
+            mask: 0x0001,
+            button: 38,
+            count: 2,
+        },
+        Packet::KeyUp {
+            id: 'a' as u16,
+            mask: 0x0001,
+            button: 38,
+        },
+        Packet::MouseWheel {
+            x_delta: -120,
+            y_delta: 240,
+        },
+        Packet::MouseMoveAbs { x: 100, y: 200 },
+        Packet::MouseMove { x: -5, y: 7 },
+        Packet::GrabClipboard { id: 0, seq_num: 3 },
+        Packet::CursorLeave,
+        Packet::Close,
+    ];
+    assert_eq!(round_trip(&packets), None);
+}
+
+#[test]
+fn test_clipboard_round_trip() {
+    let text = b"Hello, world!";
+    let mut data = Vec::new();
+    data.extend_from_slice(&1u32.to_be_bytes());
+    data.extend_from_slice(&(ClipboardFormat::Text as u32).to_be_bytes());
+    data.extend_from_slice(&(text.len() as u32).to_be_bytes());
+    data.extend_from_slice(text);
+    let size = data.len().to_string();
+    // Barrier splits large clipboards over several chunks
+    let (first, second) = data.split_at(10);
+    let marks = [
+        (ClipboardMark::Start, size.as_bytes()),
+        (ClipboardMark::Chunk, first),
+        (ClipboardMark::Chunk, second),
+        (ClipboardMark::End, &[]),
+    ];
+    let packets = marks.map(|(mark, data)| Packet::ClipboardData {
+        id: 1,
+        seq_num: 7,
+        mark,
+        data,
+    });
+    assert_eq!(round_trip(&packets).as_deref(), Some("Hello, world!"));
+}
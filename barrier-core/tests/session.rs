@@ -0,0 +1,533 @@
+mod common;
+
+use barrier_core::{
+    barrier::{
+        self, ConnectionError, OptionList, Options, Packet, OPTION_HALF_DUPLEX_CAPS_LOCK,
+        OPTION_HEARTBEAT,
+    },
+    synergy_hid::{
+        KeyboardLeds, KeyboardMode, MouseMode, Protocol, ReportType, ScanCodes, UnicodeInput,
+        MODIFIER_NUM_LOCK,
+    },
+};
+use common::{duplex, recording_actuator, ClientHello, Event, FakeServer, RecordingActuator, Step};
+use futures::{executor::block_on, future::join};
+
+const SCREEN_NAME: &str = "pico";
+
+/**
+ * Runs the client against a fake server speaking Barrier 1.6 that plays the script and closes
+ * the connection.
+ */
+fn run_session(actor: &mut RecordingActuator, script: &[Step]) -> Result<(), ConnectionError> {
+    let (client, server) = duplex();
+    let (ret, _) = block_on(join(
+        barrier::start(client, SCREEN_NAME, actor, &mut || {}),
+        async {
+            let (mut server, _) = FakeServer::accept(server, "Barrier", 1, 6).await.unwrap();
+            server.run(script).await;
+            server.send(Packet::Close).await;
+        },
+    ));
+    ret
+}
+
+fn device_info(x: u16, y: u16) -> Packet<'static> {
+    Packet::DeviceInfo {
+        x,
+        y,
+        w: 1920,
+        h: 1080,
+        _dummy: 0,
+        mx: 0,
+        my: 0,
+    }
+}
+
+#[test]
+fn test_handshake() {
+    let mut actor = recording_actuator(1920, 1080);
+    let (client, server) = duplex();
+    let (ret, hello) = block_on(join(
+        barrier::start(client, SCREEN_NAME, &mut actor, &mut || {}),
+        async {
+            let (mut server, hello) = FakeServer::accept(server, "Synergy", 1, 8).await.unwrap();
+            server.send(Packet::Close).await;
+            hello
+        },
+    ));
+    assert!(ret.is_ok());
+    // The client answers with the server's greeting and its own, older minor version
+    assert_eq!(
+        hello,
+        ClientHello {
+            literal: "Synergy".into(),
+            major: 1,
+            minor: 6,
+            name: SCREEN_NAME.into(),
+        }
+    );
+    assert_eq!(actor.sink().events.first(), Some(&Event::Connected));
+    assert_eq!(actor.sink().events.last(), Some(&Event::Disconnected));
+}
+
+#[test]
+fn test_incompatible_version() {
+    let mut actor = recording_actuator(1920, 1080);
+    let (client, server) = duplex();
+    let (ret, _) = block_on(join(
+        barrier::start(client, SCREEN_NAME, &mut actor, &mut || {}),
+        async {
+            // The client hangs up without replying
+            assert!(FakeServer::accept(server, "Barrier", 2, 0).await.is_err());
+        },
+    ));
+    assert!(matches!(
+        ret,
+        Err(ConnectionError::IncompatibleVersion { major: 2, minor: 0 })
+    ));
+    assert!(actor.sink().events.is_empty());
+}
+
+#[test]
+fn test_query_info_and_keep_alive() {
+    let mut actor = recording_actuator(1920, 1080);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::QueryInfo),
+            Step::Expect(device_info(0, 0)),
+            Step::Send(Packet::InfoAck),
+            Step::Send(Packet::KeepAlive),
+            Step::Expect(Packet::KeepAlive),
+            Step::Send(Packet::MouseMoveAbs { x: 960, y: 540 }),
+            Step::Send(Packet::QueryInfo),
+            Step::Expect(device_info(960, 540)),
+        ],
+    );
+    assert!(ret.is_ok());
+}
+
+#[test]
+fn test_typing() {
+    let mut actor = recording_actuator(1920, 1080);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::CursorEnter {
+                x: 0,
+                y: 0,
+                seq_num: 1,
+                mask: 0,
+            }),
+            Step::Send(Packet::KeyDown {
+                id: 'a' as u16,
+                mask: 0,
+                button: 38,
+            }),
+            Step::Send(Packet::KeyDown {
+                id: 'b' as u16,
+                mask: 0,
+                button: 56,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 'a' as u16,
+                mask: 0,
+                button: 38,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 'b' as u16,
+                mask: 0,
+                button: 56,
+            }),
+            Step::Send(Packet::CursorLeave),
+        ],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().reports(ReportType::Keyboard),
+        vec![
+            vec![0, 0, 0x04, 0, 0, 0, 0, 0],
+            vec![0, 0, 0x04, 0x05, 0, 0, 0, 0],
+            vec![0, 0, 0x05, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            // Released on leave and on disconnect
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+        ]
+    );
+    assert_eq!(
+        actor
+            .sink()
+            .events
+            .iter()
+            .filter(|e| matches!(e, Event::Enter | Event::Leave))
+            .collect::<Vec<_>>(),
+        vec![&Event::Enter, &Event::Leave]
+    );
+}
+
+#[test]
+fn test_nkro_keyboard() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.hid_mut().set_keyboard_mode(KeyboardMode::Nkro);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::KeyDown {
+                id: 'a' as u16,
+                mask: 0,
+                button: 38,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 'a' as u16,
+                mask: 0,
+                button: 38,
+            }),
+        ],
+    );
+    assert!(ret.is_ok());
+    let reports = actor.sink().reports(ReportType::Keyboard);
+    assert_eq!(reports.len(), 3);
+    assert!(reports.iter().all(|r| r.len() == 29));
+    assert_eq!(reports[0][..2], [0, 0x10]);
+    assert!(reports[1..].iter().all(|r| r.iter().all(|&b| b == 0)));
+}
+
+// BIOS setup screens get boot reports from an NKRO keyboard
+#[test]
+fn test_nkro_keyboard_boot_protocol() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.hid_mut().set_keyboard_mode(KeyboardMode::Nkro);
+    actor.sink_mut().keyboard_protocol = Protocol::Boot;
+    let ret = run_session(
+        &mut actor,
+        &[Step::Send(Packet::KeyDown {
+            id: 'a' as u16,
+            mask: 0,
+            button: 38,
+        })],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().reports(ReportType::Keyboard),
+        vec![vec![0, 0, 0x04, 0, 0, 0, 0, 0], vec![0; 8]]
+    );
+}
+
+// Not on a US keyboard, typed as Ctrl+Shift+U 00e9 Space
+#[test]
+fn test_unicode_input() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.hid_mut().set_unicode_input(Some(UnicodeInput::Linux));
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::KeyDown {
+                id: 'é' as u16,
+                mask: 0,
+                button: 38,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 'é' as u16,
+                mask: 0,
+                button: 38,
+            }),
+        ],
+    );
+    assert!(ret.is_ok());
+    let keys = actor
+        .sink()
+        .reports(ReportType::Keyboard)
+        .iter()
+        .map(|r| (r[0], r[2]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            (0x03, 0x18),
+            (0x03, 0),
+            (0, 0),
+            (0, 0x27),
+            (0, 0),
+            (0, 0x27),
+            (0, 0),
+            (0, 0x08),
+            (0, 0),
+            (0, 0x26),
+            (0, 0),
+            (0, 0x2C),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_codes() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.hid_mut().set_scan_codes(Some(ScanCodes::Windows));
+    // 'a' on an AZERTY server is the key next to Tab, scan code 0x10
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::KeyDown {
+                id: 'a' as u16,
+                mask: 0,
+                button: 0x10,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 'a' as u16,
+                mask: 0,
+                button: 0x10,
+            }),
+        ],
+    );
+    assert!(ret.is_ok());
+    let keys = actor
+        .sink()
+        .reports(ReportType::Keyboard)
+        .iter()
+        .map(|r| (r[0], r[2]))
+        .collect::<Vec<_>>();
+    assert_eq!(keys, [(0, 0x14), (0, 0), (0, 0)]);
+}
+
+#[test]
+fn test_mouse() {
+    let mut actor = recording_actuator(1920, 1080);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::MouseMoveAbs { x: 960, y: 540 }),
+            Step::Send(Packet::MouseDown { id: 1 }),
+            Step::Send(Packet::MouseMove { x: 10, y: -10 }),
+            Step::Send(Packet::MouseUp { id: 1 }),
+            Step::Send(Packet::MouseWheel {
+                x_delta: 0,
+                y_delta: 120,
+            }),
+        ],
+    );
+    assert!(ret.is_ok());
+    let (x, y) = actor.scale_position(960, 540);
+    let (x2, y2) = actor.scale_position(970, 530);
+    let [x, y, x2, y2] = [x, y, x2, y2].map(u16::to_le_bytes);
+    assert_eq!(
+        actor.sink().reports(ReportType::Mouse),
+        vec![
+            vec![0, x[0], x[1], y[0], y[1], 0, 0],
+            vec![1, x[0], x[1], y[0], y[1], 0, 0],
+            vec![1, x2[0], x2[1], y2[0], y2[1], 0, 0],
+            vec![0, x2[0], x2[1], y2[0], y2[1], 0, 0],
+            vec![0, x2[0], x2[1], y2[0], y2[1], 1, 0],
+            // Released on disconnect
+            vec![0, x2[0], x2[1], y2[0], y2[1], 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn test_relative_mouse() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.hid_mut().set_mouse_mode(MouseMode::Relative);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::MouseMoveAbs { x: 100, y: 50 }),
+            Step::Send(Packet::MouseDown { id: 1 }),
+            Step::Send(Packet::MouseMove { x: 300, y: -60 }),
+            Step::Send(Packet::MouseUp { id: 1 }),
+            Step::Send(Packet::MouseWheel {
+                x_delta: 0,
+                y_delta: 120,
+            }),
+            Step::Send(Packet::QueryInfo),
+            // The cursor can't go past the top of the screen
+            Step::Expect(device_info(400, 0)),
+        ],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().reports(ReportType::Mouse),
+        vec![
+            vec![0, 100, 50, 0, 0],
+            vec![1, 0, 0, 0, 0],
+            // Split to fit the report
+            vec![1, 127, (-60i8) as u8, 0, 0],
+            vec![1, 127, 0, 0, 0],
+            vec![1, 46, 0, 0, 0],
+            vec![0, 0, 0, 0, 0],
+            vec![0, 0, 0, 1, 0],
+            // Released on disconnect
+            vec![0, 0, 0, 0, 0],
+        ]
+    );
+}
+
+// BIOS setup screens pick the boot protocol, the absolute mouse falls back to boot reports
+#[test]
+fn test_boot_mouse() {
+    let mut actor = recording_actuator(1920, 1080);
+    actor.sink_mut().mouse_protocol = Protocol::Boot;
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::MouseMoveAbs { x: 200, y: 100 }),
+            Step::Send(Packet::MouseMove { x: -10, y: 10 }),
+            Step::Send(Packet::MouseDown { id: 1 }),
+        ],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().reports(ReportType::Mouse),
+        vec![
+            vec![0, 127, 100],
+            vec![0, 73, 0],
+            vec![0, (-10i8) as u8, 10],
+            vec![1, 0, 0],
+            // Released on disconnect
+            vec![0, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn test_enter() {
+    let mut actor = recording_actuator(1920, 1080);
+    // The host has caps lock on, the server num lock
+    actor.sink_mut().leds = KeyboardLeds {
+        caps_lock: true,
+        ..KeyboardLeds::default()
+    };
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::CursorEnter {
+                x: 960,
+                y: 540,
+                seq_num: 1,
+                mask: MODIFIER_NUM_LOCK,
+            }),
+            Step::Send(Packet::QueryInfo),
+            Step::Expect(device_info(960, 540)),
+        ],
+    );
+    assert!(ret.is_ok());
+    let (x, y) = actor.scale_position(960, 540);
+    let [x, y] = [x, y].map(u16::to_le_bytes);
+    assert_eq!(
+        actor.sink().events[..5],
+        [
+            Event::Connected,
+            Event::Report(ReportType::Mouse, vec![0, x[0], x[1], y[0], y[1], 0, 0]),
+            // Both are tapped to match the server
+            Event::Report(ReportType::Keyboard, vec![0, 0, 0x39, 0x53, 0, 0, 0, 0]),
+            Event::Report(ReportType::Keyboard, vec![0; 8]),
+            Event::Enter,
+        ]
+    );
+}
+
+#[test]
+fn test_options_and_clipboard() {
+    let mut actor = recording_actuator(1920, 1080);
+    let mut options = OptionList::default();
+    options.push(OPTION_HEARTBEAT, 5000);
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::ResetOptions),
+            Step::Send(Packet::SetOptions(options)),
+            Step::Send(Packet::GrabClipboard { id: 0, seq_num: 3 }),
+            Step::SendClipboard {
+                id: 0,
+                seq_num: 3,
+                text: "Hello, world!".into(),
+            },
+        ],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().events,
+        vec![
+            Event::Connected,
+            Event::ResetOptions,
+            Event::Options(Options {
+                heartbeat_ms: 5000,
+                ..Options::default()
+            }),
+            Event::Clipboard(0, "Hello, world!".into()),
+            Event::Report(ReportType::Keyboard, vec![0; 8]),
+            Event::Report(ReportType::Mouse, vec![0; 7]),
+            Event::Report(ReportType::Consumer, vec![0; 2]),
+            Event::Disconnected,
+        ]
+    );
+}
+
+// With half-duplex caps lock the server only sends key down to turn it on and key up to turn it
+// off, each of them is a whole tap for the host
+#[test]
+fn test_half_duplex_caps_lock() {
+    let mut actor = recording_actuator(1920, 1080);
+    let mut options = OptionList::default();
+    options.push(OPTION_HALF_DUPLEX_CAPS_LOCK, 1);
+    let caps_lock = [0, 0, 0x39, 0, 0, 0, 0, 0];
+    let ret = run_session(
+        &mut actor,
+        &[
+            Step::Send(Packet::SetOptions(options)),
+            Step::Send(Packet::KeyDown {
+                id: 0xEFE5,
+                mask: 0,
+                button: 0x3A,
+            }),
+            Step::Send(Packet::KeyUp {
+                id: 0xEFE5,
+                mask: 0x1000,
+                button: 0x3A,
+            }),
+        ],
+    );
+    assert!(ret.is_ok());
+    assert_eq!(
+        actor.sink().reports(ReportType::Keyboard),
+        vec![
+            caps_lock.to_vec(),
+            vec![0; 8],
+            caps_lock.to_vec(),
+            vec![0; 8],
+            // Released on disconnect
+            vec![0; 8],
+        ]
+    );
+}
+
+#[test]
+fn test_server_errors() {
+    let mut actor = recording_actuator(1920, 1080);
+    let ret = run_session(&mut actor, &[Step::Send(Packet::ErrorBusy)]);
+    assert!(matches!(ret, Err(ConnectionError::NameInUse)));
+
+    let mut actor = recording_actuator(1920, 1080);
+    let ret = run_session(&mut actor, &[Step::Send(Packet::ErrorUnknownDevice)]);
+    assert!(matches!(ret, Err(ConnectionError::UnknownName)));
+}
+
+#[test]
+fn test_connection_lost() {
+    let mut actor = recording_actuator(1920, 1080);
+    let (client, server) = duplex();
+    let (ret, _) = block_on(join(
+        barrier::start(client, SCREEN_NAME, &mut actor, &mut || {}),
+        async {
+            let (server, _) = FakeServer::accept(server, "Barrier", 1, 6).await.unwrap();
+            drop(server);
+        },
+    ));
+    assert!(ret.is_err());
+    assert_eq!(actor.sink().events.last(), Some(&Event::Disconnected));
+}
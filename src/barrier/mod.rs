@@ -1,13 +0,0 @@
-mod error;
-mod actuator;
-mod packet;
-mod packet_io;
-mod packet_stream;
-mod take;
-mod client;
-
-pub use error::*;
-pub use packet::*;
-pub use packet_io::*;
-pub use actuator::Actuator;
-pub use client::start;
\ No newline at end of file
@@ -0,0 +1,247 @@
+use core::mem::MaybeUninit;
+
+use defmt::*;
+use embassy_usb::class::hid::{Config, ReportId, RequestHandler};
+use embassy_usb::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use embassy_usb::driver::{Driver, Endpoint, EndpointError, EndpointIn};
+use embassy_usb::types::InterfaceNumber;
+use embassy_usb::{Builder, Handler};
+
+use barrier_core::synergy_hid::Protocol;
+
+use crate::host_state::HostProtocol;
+
+const USB_CLASS_HID: u8 = 0x03;
+const USB_SUBCLASS_BOOT: u8 = 0x01;
+pub const USB_PROTOCOL_KEYBOARD: u8 = 0x01;
+pub const USB_PROTOCOL_MOUSE: u8 = 0x02;
+
+const HID_DESC_DESCTYPE_HID: u8 = 0x21;
+const HID_DESC_DESCTYPE_HID_REPORT: u8 = 0x22;
+const HID_DESC_SPEC_1_10: [u8; 2] = [0x10, 0x01];
+const HID_DESC_COUNTRY_UNSPEC: u8 = 0x00;
+
+const HID_REQ_GET_REPORT: u8 = 0x01;
+const HID_REQ_GET_IDLE: u8 = 0x02;
+const HID_REQ_GET_PROTOCOL: u8 = 0x03;
+const HID_REQ_SET_REPORT: u8 = 0x09;
+const HID_REQ_SET_IDLE: u8 = 0x0a;
+const HID_REQ_SET_PROTOCOL: u8 = 0x0b;
+
+// Request handler of the interface and the protocol the host picked
+pub struct State<'d> {
+    control: MaybeUninit<Control<'d>>,
+    protocol: HostProtocol,
+}
+
+impl<'d> State<'d> {
+    pub const fn new() -> Self {
+        Self {
+            control: MaybeUninit::uninit(),
+            protocol: HostProtocol::new(Protocol::Report as u8),
+        }
+    }
+}
+
+/**
+ * HID interface with the boot subclass, so BIOS, UEFI setup screens and bootloaders find the
+ * keyboard and the mouse. embassy-usb's HID class always declares subclass 0 and rejects
+ * SET_PROTOCOL to boot, this one records the protocol for `SynergyHid` to pick the report format.
+ * There is no OUT endpoint, hosts send the keyboard LEDs with SET_REPORT instead.
+ */
+pub struct BootHidWriter<'d, D: Driver<'d>> {
+    ep_in: D::EndpointIn,
+    protocol: &'d HostProtocol,
+}
+
+impl<'d, D: Driver<'d>> BootHidWriter<'d, D> {
+    // `interface_protocol` is `USB_PROTOCOL_KEYBOARD` or `USB_PROTOCOL_MOUSE`
+    pub fn new(
+        builder: &mut Builder<'d, D>,
+        state: &'d mut State<'d>,
+        interface_protocol: u8,
+        config: Config<'d>,
+    ) -> Self {
+        let len = config.report_descriptor.len();
+        let mut func = builder.function(USB_CLASS_HID, USB_SUBCLASS_BOOT, interface_protocol);
+        let mut iface = func.interface();
+        let if_num = iface.interface_number();
+        let mut alt = iface.alt_setting(USB_CLASS_HID, USB_SUBCLASS_BOOT, interface_protocol, None);
+        let descriptor = [
+            HID_DESC_SPEC_1_10[0],
+            HID_DESC_SPEC_1_10[1],
+            HID_DESC_COUNTRY_UNSPEC,
+            // One report descriptor follows
+            1,
+            HID_DESC_DESCTYPE_HID_REPORT,
+            len as u8,
+            (len >> 8) as u8,
+        ];
+        alt.descriptor(HID_DESC_DESCTYPE_HID, &descriptor);
+        let ep_in = alt.endpoint_interrupt_in(config.max_packet_size, config.poll_ms);
+        drop(func);
+
+        let control = state.control.write(Control::new(
+            if_num,
+            config.report_descriptor,
+            descriptor,
+            config.request_handler,
+            &state.protocol,
+        ));
+        builder.handler(control);
+        Self {
+            ep_in,
+            protocol: &state.protocol,
+        }
+    }
+
+    // Can be kept to check the protocol without the writer
+    pub fn protocol(&self) -> &'d HostProtocol {
+        self.protocol
+    }
+
+    pub async fn ready(&mut self) {
+        self.ep_in.wait_enabled().await;
+    }
+
+    // Reports are shorter than a packet, so they always go in one
+    pub async fn write(&mut self, report: &[u8]) -> Result<(), EndpointError> {
+        self.ep_in.write(report).await
+    }
+}
+
+struct Control<'d> {
+    if_num: InterfaceNumber,
+    report_descriptor: &'d [u8],
+    hid_descriptor: [u8; 9],
+    request_handler: Option<&'d dyn RequestHandler>,
+    protocol: &'d HostProtocol,
+}
+
+impl<'d> Control<'d> {
+    fn new(
+        if_num: InterfaceNumber,
+        report_descriptor: &'d [u8],
+        descriptor: [u8; 7],
+        request_handler: Option<&'d dyn RequestHandler>,
+        protocol: &'d HostProtocol,
+    ) -> Self {
+        let mut hid_descriptor = [0; 9];
+        hid_descriptor[0] = hid_descriptor.len() as u8;
+        hid_descriptor[1] = HID_DESC_DESCTYPE_HID;
+        hid_descriptor[2..].copy_from_slice(&descriptor);
+        Self {
+            if_num,
+            report_descriptor,
+            hid_descriptor,
+            request_handler,
+            protocol,
+        }
+    }
+}
+
+fn report_id(value: u16) -> Option<ReportId> {
+    let id = value as u8;
+    match (value >> 8) as u8 {
+        1 => Some(ReportId::In(id)),
+        2 => Some(ReportId::Out(id)),
+        3 => Some(ReportId::Feature(id)),
+        _ => None,
+    }
+}
+
+impl<'d> Handler for Control<'d> {
+    fn reset(&mut self) {
+        // Devices come out of a reset in the report protocol
+        self.protocol.set(Protocol::Report as u8);
+    }
+
+    fn control_out(&mut self, req: Request, data: &[u8]) -> Option<OutResponse> {
+        if (req.request_type, req.recipient, req.index)
+            != (
+                RequestType::Class,
+                Recipient::Interface,
+                self.if_num.0 as u16,
+            )
+        {
+            return None;
+        }
+        match req.request {
+            HID_REQ_SET_IDLE => {
+                if let Some(handler) = self.request_handler {
+                    let id = req.value as u8;
+                    let id = (id != 0).then_some(ReportId::In(id));
+                    let dur = u32::from(req.value >> 8);
+                    let dur = if dur == 0 { u32::MAX } else { 4 * dur };
+                    handler.set_idle_ms(id, dur);
+                }
+                Some(OutResponse::Accepted)
+            }
+            HID_REQ_SET_REPORT => match (report_id(req.value), self.request_handler) {
+                (Some(id), Some(handler)) => Some(handler.set_report(id, data)),
+                _ => Some(OutResponse::Rejected),
+            },
+            HID_REQ_SET_PROTOCOL => {
+                let protocol = if req.value == 0 {
+                    Protocol::Boot
+                } else {
+                    Protocol::Report
+                };
+                info!(
+                    "Interface {} switched to the {} protocol",
+                    self.if_num.0, protocol
+                );
+                self.protocol.set(protocol as u8);
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(OutResponse::Rejected),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if req.index != self.if_num.0 as u16 {
+            return None;
+        }
+        match (req.request_type, req.recipient) {
+            (RequestType::Standard, Recipient::Interface) => match req.request {
+                Request::GET_DESCRIPTOR => match (req.value >> 8) as u8 {
+                    HID_DESC_DESCTYPE_HID_REPORT => {
+                        Some(InResponse::Accepted(self.report_descriptor))
+                    }
+                    HID_DESC_DESCTYPE_HID => Some(InResponse::Accepted(&self.hid_descriptor)),
+                    _ => Some(InResponse::Rejected),
+                },
+                _ => Some(InResponse::Rejected),
+            },
+            (RequestType::Class, Recipient::Interface) => match req.request {
+                HID_REQ_GET_REPORT => {
+                    let size = match (report_id(req.value), self.request_handler) {
+                        (Some(id), Some(handler)) => handler.get_report(id, buf),
+                        _ => None,
+                    };
+                    match size {
+                        Some(len) => Some(InResponse::Accepted(&buf[..len])),
+                        None => Some(InResponse::Rejected),
+                    }
+                }
+                HID_REQ_GET_IDLE => {
+                    let id = req.value as u8;
+                    let id = (id != 0).then_some(ReportId::In(id));
+                    match self.request_handler.and_then(|h| h.get_idle_ms(id)) {
+                        Some(dur) => {
+                            buf[0] = u8::try_from(dur / 4).unwrap_or(0);
+                            Some(InResponse::Accepted(&buf[..1]))
+                        }
+                        None => Some(InResponse::Rejected),
+                    }
+                }
+                HID_REQ_GET_PROTOCOL => {
+                    buf[0] = self.protocol.get() as u8;
+                    Some(InResponse::Accepted(&buf[..1]))
+                }
+                _ => Some(InResponse::Rejected),
+            },
+            _ => None,
+        }
+    }
+}
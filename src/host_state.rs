@@ -0,0 +1,64 @@
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use barrier_core::synergy_hid::{KeyboardLeds, Protocol};
+
+/**
+ * Value the host sets with a one byte control request.
+ */
+pub trait HostByte {
+    fn from_byte(byte: u8) -> Self;
+}
+
+impl HostByte for u8 {
+    fn from_byte(byte: u8) -> Self {
+        byte
+    }
+}
+
+impl HostByte for KeyboardLeds {
+    fn from_byte(byte: u8) -> Self {
+        KeyboardLeds::from_report(byte)
+    }
+}
+
+impl HostByte for Protocol {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => Protocol::Boot,
+            _ => Protocol::Report,
+        }
+    }
+}
+
+/**
+ * Last byte the host set for an interface, written by the USB request handler and read by the
+ * actuator and the indicator.
+ */
+pub struct HostState<T>(AtomicU8, PhantomData<T>);
+
+impl<T> HostState<T> {
+    pub const fn new(byte: u8) -> Self {
+        Self(AtomicU8::new(byte), PhantomData)
+    }
+
+    pub fn set(&self, byte: u8) {
+        self.0.store(byte, Ordering::Relaxed);
+    }
+}
+
+impl<T: HostByte> HostState<T> {
+    pub fn get(&self) -> T {
+        T::from_byte(self.0.load(Ordering::Relaxed))
+    }
+}
+
+// LED output report of the keyboard
+pub type HostLeds = HostState<KeyboardLeds>;
+
+// Protocol the host picked with SET_PROTOCOL
+pub type HostProtocol = HostState<Protocol>;
+
+// Resolution Multiplier feature report of the mouse. Hosts that support it set it after
+// enumeration, others leave it at 0.
+pub type WheelResolution = HostState<u8>;
@@ -1,8 +1,11 @@
+use barrier_core::synergy_hid::KeyboardLeds;
 use cyw43::Control;
 use defmt::info;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Receiver, mutex::Mutex};
 use embassy_time::{with_timeout, Duration};
 
+use crate::host_state::HostLeds;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
 pub enum IndicatorStatus {
@@ -14,10 +17,17 @@ pub enum IndicatorStatus {
     EnterScreen,
     LeaveScreen,
     ServerDisconnected,
+    IncompatibleServer,
+    NameInUse,
+    UnknownName,
+    ProtocolError,
+    TlsFailed,
 }
 
-fn get_duty_cycle(status: IndicatorStatus) -> (u64, u64) {
+fn get_duty_cycle(status: IndicatorStatus, leds: KeyboardLeds) -> (u64, u64) {
     match status {
+        // Blinks off briefly while caps lock is on, it's easy to miss on the host
+        IndicatorStatus::EnterScreen if leds.caps_lock => (900, 100),
         IndicatorStatus::PowerOn => (50, 50),
         IndicatorStatus::WifiConnecting => (50, 50),
         IndicatorStatus::WifiConnected => (100, 100),
@@ -26,6 +36,11 @@ fn get_duty_cycle(status: IndicatorStatus) -> (u64, u64) {
         IndicatorStatus::EnterScreen => (1000, 0),
         IndicatorStatus::LeaveScreen => (500, 500),
         IndicatorStatus::ServerDisconnected => (100, 100),
+        IndicatorStatus::IncompatibleServer => (100, 1900),
+        IndicatorStatus::NameInUse => (1500, 500),
+        IndicatorStatus::UnknownName => (100, 900),
+        IndicatorStatus::ProtocolError => (250, 750),
+        IndicatorStatus::TlsFailed => (50, 950),
     }
 }
 
@@ -33,11 +48,12 @@ fn get_duty_cycle(status: IndicatorStatus) -> (u64, u64) {
 pub async fn indicator_task(
     receiver: Receiver<'static, NoopRawMutex, IndicatorStatus, 4>,
     control: &'static Mutex<NoopRawMutex, Control<'static>>,
+    keyboard_leds: &'static HostLeds,
 ) {
     let mut current_status = IndicatorStatus::PowerOn;
     let mut led_on = true;
     loop {
-        let (on, off) = get_duty_cycle(current_status);
+        let (on, off) = get_duty_cycle(current_status, keyboard_leds.get());
         match current_status {
             IndicatorStatus::EnterScreen => {
                 control
@@ -3,13 +3,15 @@
 #![feature(type_alias_impl_trait)]
 #![allow(async_fn_in_trait)]
 
+extern crate alloc;
+
 use const_env::from_env;
 use core::sync::atomic::{AtomicBool, Ordering};
 use cyw43::Control;
 use cyw43_pio::PioSpi;
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_futures::join::join5;
+use embassy_futures::join::join4;
 use embassy_net::tcp::TcpSocket;
 use embassy_net::{Config, IpEndpoint, Ipv4Address, Stack, StackResources};
 use embassy_rp::bind_interrupts;
@@ -20,20 +22,34 @@ use embassy_rp::watchdog::Watchdog;
 use embassy_sync::channel::Sender;
 use embassy_sync::mutex::Mutex;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel};
-use embassy_time::{with_timeout, Duration, Timer};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
 use embassy_usb::class::hid::{HidReaderWriter, ReportId, RequestHandler};
 use embassy_usb::control::OutResponse;
+use embedded_alloc::Heap;
+use host_state::{HostLeds, WheelResolution};
 use indicator::IndicatorStatus;
+use paste::Paste;
 use static_cell::make_static;
 
-use crate::synergy_hid::SynergyHid;
+use barrier_core::barrier::{self, Capture, ConnectionError};
+use barrier_core::hid_actuator::HidActuator;
+use barrier_core::synergy_hid::{
+    self, KeyboardLeds, KeyboardMode, Layout, MouseMode, ScanCodes, SynergyHid, UnicodeInput,
+};
 use {defmt_rtt as _, panic_probe as _};
 
-mod barrier;
+mod boot_hid;
+mod host_state;
 mod indicator;
-mod synergy_hid;
+mod paste;
+mod tls;
 mod usb_actuator;
 
+// Only used by the RSA signature check of the TLS handshake
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+const HEAP_SIZE: usize = 16 * 1024;
+
 bind_interrupts!(struct Irqs {
     USBCTRL_IRQ => embassy_rp::usb::InterruptHandler<USB>;
     PIO0_IRQ_0 => InterruptHandler<PIO0>;
@@ -43,6 +59,11 @@ const WIFI_NETWORK: &str = env!("WIFI_NETWORK");
 const WIFI_PASSWORD: &str = env!("WIFI_PASSWORD");
 const SCREEN_NAME: &str = env!("SCREEN_NAME");
 const SERVER_ENDPOINT: &str = env!("SERVER_ENDPOINT");
+// SHA-256 fingerprint of the server certificate, TLS is used only when this is set
+const SERVER_FINGERPRINT: Option<&str> = option_env!("SERVER_FINGERPRINT");
+const KEYBOARD_LAYOUT: Option<&str> = option_env!("KEYBOARD_LAYOUT");
+const UNICODE_INPUT: Option<&str> = option_env!("UNICODE_INPUT");
+const SCAN_CODES: Option<&str> = option_env!("SCAN_CODES");
 #[from_env]
 const SCREEN_WIDTH: u16 = 1920;
 #[from_env]
@@ -50,7 +71,33 @@ const SCREEN_HEIGHT: u16 = 1080;
 #[from_env]
 const FLIP_MOUSE_WHEEL: bool = false;
 #[from_env]
+const RELATIVE_MOUSE: bool = false;
+#[from_env]
+const NKRO_KEYBOARD: bool = false;
+#[from_env]
 const WATCHDOG_INTERVAL: u64 = 8;
+#[from_env]
+const PASTE_HOTKEY: u16 = 0;
+#[from_env]
+const PASTE_HOTKEY_MASK: u16 = 0;
+#[from_env]
+const PASTE_MAX_LENGTH: usize = 1024;
+#[from_env]
+const PASTE_KEY_DELAY_MS: u64 = 5;
+#[from_env]
+const CAPTURE_BUFFER_SIZE: usize = 0;
+
+const KEYBOARD_MODE: KeyboardMode = if NKRO_KEYBOARD {
+    KeyboardMode::Nkro
+} else {
+    KeyboardMode::SixKey
+};
+
+const MOUSE_MODE: MouseMode = if RELATIVE_MOUSE {
+    MouseMode::Relative
+} else {
+    MouseMode::Absolute
+};
 
 fn parse_addr(s: &str) -> Ipv4Address {
     let mut parts = s.split('.');
@@ -74,6 +121,57 @@ fn parse_endpoint(s: &str) -> IpEndpoint {
     IpEndpoint::from((ip, port))
 }
 
+// Configuration errors won't go away quickly, so don't hammer the server
+fn retry_delay(e: &ConnectionError) -> Duration {
+    match e {
+        ConnectionError::IncompatibleVersion { .. } => Duration::from_secs(60),
+        ConnectionError::UnknownName | ConnectionError::TlsError => Duration::from_secs(30),
+        ConnectionError::NameInUse => Duration::from_secs(10),
+        ConnectionError::BadMessage => Duration::from_secs(5),
+        _ => Duration::from_secs(1),
+    }
+}
+
+fn error_status(e: &ConnectionError) -> IndicatorStatus {
+    match e {
+        ConnectionError::IncompatibleVersion { .. } => IndicatorStatus::IncompatibleServer,
+        ConnectionError::UnknownName => IndicatorStatus::UnknownName,
+        ConnectionError::NameInUse => IndicatorStatus::NameInUse,
+        ConnectionError::BadMessage => IndicatorStatus::ProtocolError,
+        ConnectionError::TlsError => IndicatorStatus::TlsFailed,
+        _ => IndicatorStatus::ServerDisconnected,
+    }
+}
+
+async fn wait_feeding_watchdog(watchdog: &mut Watchdog, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    loop {
+        watchdog.feed();
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        Timer::after(core::cmp::min(deadline - now, Duration::from_secs(1))).await;
+    }
+}
+
+/**
+ * Logs the capture as hex, the lines can be turned back into a file for `barrier-sim --replay`.
+ */
+struct CaptureLog;
+
+impl embedded_io_async::ErrorType for CaptureLog {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_io_async::Write for CaptureLog {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = core::cmp::min(buf.len(), 64);
+        info!("Capture: {=[u8]:02x}", &buf[..n]);
+        Ok(n)
+    }
+}
+
 #[embassy_executor::task]
 async fn wifi_task(
     runner: cyw43::Runner<
@@ -92,6 +190,26 @@ async fn net_task(stack: &'static Stack<cyw43::NetDriver<'static>>) -> ! {
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
+    {
+        use core::mem::MaybeUninit;
+        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+        unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
+    }
+    if let Some(fingerprint) = SERVER_FINGERPRINT {
+        barrier_core::tls::parse_fingerprint(fingerprint).expect("invalid server fingerprint");
+    }
+    let layout = KEYBOARD_LAYOUT
+        .map_or(Ok(Layout::Us), str::parse)
+        .expect("invalid keyboard layout");
+    let unicode_input: Option<UnicodeInput> = UNICODE_INPUT
+        .map(str::parse)
+        .transpose()
+        .expect("invalid unicode input");
+    let scan_codes: Option<ScanCodes> = SCAN_CODES
+        .map(str::parse)
+        .transpose()
+        .expect("invalid scan codes");
+
     let p = embassy_rp::init(Default::default());
     let driver = embassy_rp::usb::Driver::new(p.USB, Irqs);
     let mut watchdog = Watchdog::new(p.WATCHDOG);
@@ -116,11 +234,26 @@ async fn main(spawner: Spawner) {
     // You can also add a Microsoft OS descriptor.
     let mut msos_descriptor = [0; 256];
     let mut control_buf = [0; 64];
-    let request_handler = MyRequestHandler {};
-    let mut device_handler = MyDeviceHandler::new();
+    let keyboard_leds: &'static HostLeds = make_static!(HostLeds::new(0));
+    let wheel_resolution: &'static WheelResolution = make_static!(WheelResolution::new(0));
+    // Each interface has its own handler, so only the keyboard's output report sets the LEDs and
+    // only the mouse's feature report the Resolution Multiplier
+    let keyboard_handler = MyRequestHandler {
+        keyboard_leds: Some(keyboard_leds),
+        wheel_resolution: None,
+    };
+    let mouse_handler = MyRequestHandler {
+        keyboard_leds: None,
+        wheel_resolution: Some(wheel_resolution),
+    };
+    let consumer_handler = MyRequestHandler {
+        keyboard_leds: None,
+        wheel_resolution: None,
+    };
+    let mut device_handler = MyDeviceHandler::new(wheel_resolution);
 
-    let mut keyboard_state = embassy_usb::class::hid::State::new();
-    let mut mouse_state = embassy_usb::class::hid::State::new();
+    let mut keyboard_state = boot_hid::State::new();
+    let mut mouse_state = boot_hid::State::new();
     let mut consumer_state = embassy_usb::class::hid::State::new();
 
     let mut builder = embassy_usb::Builder::new(
@@ -137,22 +270,32 @@ async fn main(spawner: Spawner) {
 
     // Create classes on the builder.
     let config = embassy_usb::class::hid::Config {
-        report_descriptor: SynergyHid::get_report_descriptor(synergy_hid::ReportType::Keyboard).1,
-        request_handler: Some(&request_handler),
+        report_descriptor: SynergyHid::get_keyboard_report_descriptor(KEYBOARD_MODE).1,
+        request_handler: Some(&keyboard_handler),
         poll_ms: 10,
         max_packet_size: 64,
     };
-    let keyboard = HidReaderWriter::<_, 1, 8>::new(&mut builder, &mut keyboard_state, config);
+    let keyboard_writer = boot_hid::BootHidWriter::new(
+        &mut builder,
+        &mut keyboard_state,
+        boot_hid::USB_PROTOCOL_KEYBOARD,
+        config,
+    );
     let config = embassy_usb::class::hid::Config {
-        report_descriptor: SynergyHid::get_report_descriptor(synergy_hid::ReportType::Mouse).1,
-        request_handler: Some(&request_handler),
+        report_descriptor: SynergyHid::get_mouse_report_descriptor(MOUSE_MODE).1,
+        request_handler: Some(&mouse_handler),
         poll_ms: 5,
         max_packet_size: 64,
     };
-    let mouse = HidReaderWriter::<_, 1, 7>::new(&mut builder, &mut mouse_state, config);
+    let mouse_writer = boot_hid::BootHidWriter::new(
+        &mut builder,
+        &mut mouse_state,
+        boot_hid::USB_PROTOCOL_MOUSE,
+        config,
+    );
     let config = embassy_usb::class::hid::Config {
         report_descriptor: SynergyHid::get_report_descriptor(synergy_hid::ReportType::Consumer).1,
-        request_handler: Some(&request_handler),
+        request_handler: Some(&consumer_handler),
         poll_ms: 10,
         max_packet_size: 64,
     };
@@ -203,7 +346,8 @@ async fn main(spawner: Spawner) {
 
     unwrap!(spawner.spawn(indicator::indicator_task(
         indicator_channel.receiver(),
-        control
+        control,
+        keyboard_leds
     )));
 
     sender.send(IndicatorStatus::WifiConnecting).await;
@@ -267,35 +411,49 @@ async fn main(spawner: Spawner) {
 
     let mut rx_buffer = [0; 4096];
     let mut tx_buffer = [0; 4096];
+    let mut tls_read_buffer = [0; tls::TLS_READ_BUFFER_SIZE];
+    let mut tls_write_buffer = [0; tls::TLS_WRITE_BUFFER_SIZE];
+    let mut capture_buffer = [0; CAPTURE_BUFFER_SIZE];
+    let mut capture = Capture::new(&mut capture_buffer);
 
-    let (keyboard_reader, keyboard_writer) = keyboard.split();
-
-    let (mouse_reader, mouse_writer) = mouse.split();
+    let keyboard_protocol = keyboard_writer.protocol();
+    let keyboard_writer = Mutex::<NoopRawMutex, _>::new(keyboard_writer);
+    let paste: &'static Paste = make_static!(Paste::new(PASTE_MAX_LENGTH));
 
     let (consumer_reader, consumer_writer) = consumer.split();
 
-    let keyboard_out_fut = async {
-        keyboard_reader.run(false, &request_handler).await;
-    };
-
-    let mouse_out_fut = async {
-        mouse_reader.run(false, &request_handler).await;
-    };
-
     let consumer_out_fut = async {
-        consumer_reader.run(false, &request_handler).await;
+        consumer_reader.run(false, &consumer_handler).await;
     };
 
+    let paste_fut = paste.run(
+        &keyboard_writer,
+        KEYBOARD_MODE,
+        layout,
+        unicode_input,
+        Duration::from_millis(PASTE_KEY_DELAY_MS),
+    );
+
     let in_fut = async {
-        let mut actuator = usb_actuator::UsbActuator::new(
-            SCREEN_WIDTH,
-            SCREEN_HEIGHT,
-            FLIP_MOUSE_WHEEL,
+        let mut hid = SynergyHid::new(FLIP_MOUSE_WHEEL);
+        hid.set_keyboard_mode(KEYBOARD_MODE);
+        hid.set_layout(layout);
+        hid.set_unicode_input(unicode_input);
+        hid.set_scan_codes(scan_codes);
+        hid.set_mouse_mode(MOUSE_MODE);
+        let usb = usb_actuator::UsbActuator::new(
+            paste,
+            PASTE_HOTKEY,
+            PASTE_HOTKEY_MASK,
             sender,
-            keyboard_writer,
+            keyboard_leds,
+            wheel_resolution,
+            &keyboard_writer,
+            keyboard_protocol,
             mouse_writer,
             consumer_writer,
         );
+        let mut actuator = HidActuator::new(SCREEN_WIDTH, SCREEN_HEIGHT, hid, usb);
         loop {
             sender.send(IndicatorStatus::ServerConnecting).await;
             let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
@@ -303,40 +461,106 @@ async fn main(spawner: Spawner) {
 
             let remote_endpoint = parse_endpoint(SERVER_ENDPOINT);
             info!("Connecting...");
-            let r = socket.connect(remote_endpoint).await;
-            if let Err(e) = r {
-                warn!("connect error: {:?}", e);
-                continue;
+            // Reconnecting must not take longer than the watchdog interval
+            watchdog.feed();
+            match with_timeout(
+                Duration::from_secs(WATCHDOG_INTERVAL / 2),
+                socket.connect(remote_endpoint),
+            )
+            .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    warn!("connect error: {:?}", e);
+                    continue;
+                }
+                Err(_) => {
+                    warn!("connect timed out");
+                    continue;
+                }
             }
             info!("Connected!");
             sender.send(IndicatorStatus::ServerConnected).await;
-            barrier::start(socket, SCREEN_NAME, &mut actuator, &mut watchdog)
+            let result = if SERVER_FINGERPRINT.is_some() {
+                watchdog.feed();
+                match tls::connect(socket, &mut tls_read_buffer, &mut tls_write_buffer).await {
+                    Ok(stream) => {
+                        barrier::start_with_capture(
+                            stream,
+                            SCREEN_NAME,
+                            &mut actuator,
+                            &mut || watchdog.feed(),
+                            &mut capture,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                }
+            } else {
+                barrier::start_with_capture(
+                    socket,
+                    SCREEN_NAME,
+                    &mut actuator,
+                    &mut || watchdog.feed(),
+                    &mut capture,
+                )
                 .await
-                .ok();
-            Timer::after(Duration::from_secs(1)).await;
+            };
+            if !capture.is_empty() {
+                info!(
+                    "Capture of the session, {} bytes, {} packets dropped",
+                    capture.len(),
+                    capture.dropped()
+                );
+                capture.export(CaptureLog).await.ok();
+                capture.clear();
+            }
+            let delay = match result {
+                Ok(()) => Duration::from_secs(1),
+                Err(e) => {
+                    warn!("Connection error: {:?}", e);
+                    sender.send(error_status(&e)).await;
+                    retry_delay(&e)
+                }
+            };
+            wait_feeding_watchdog(&mut watchdog, delay).await;
         }
     };
 
-    join5(
-        usb_fut,
-        in_fut,
-        keyboard_out_fut,
-        mouse_out_fut,
-        consumer_out_fut,
-    )
-    .await;
+    join4(usb_fut, in_fut, consumer_out_fut, paste_fut).await;
 }
 
-struct MyRequestHandler {}
+struct MyRequestHandler {
+    // Only set for the interface the report belongs to
+    keyboard_leds: Option<&'static HostLeds>,
+    wheel_resolution: Option<&'static WheelResolution>,
+}
 
 impl RequestHandler for MyRequestHandler {
-    fn get_report(&self, id: ReportId, _buf: &mut [u8]) -> Option<usize> {
+    fn get_report(&self, id: ReportId, buf: &mut [u8]) -> Option<usize> {
         info!("Get report for {:?}", id);
-        None
+        match (id, self.wheel_resolution) {
+            (ReportId::Feature(_), Some(wheel_resolution)) => {
+                buf[0] = wheel_resolution.get();
+                Some(1)
+            }
+            _ => None,
+        }
     }
 
     fn set_report(&self, id: ReportId, data: &[u8]) -> OutResponse {
         info!("Set report for {:?}: {=[u8]}", id, data);
+        match (id, data.first(), self.keyboard_leds, self.wheel_resolution) {
+            (ReportId::Out(_), Some(&report), Some(keyboard_leds), _) => {
+                info!("Keyboard LEDs: {}", KeyboardLeds::from_report(report));
+                keyboard_leds.set(report);
+            }
+            (ReportId::Feature(_), Some(&report), _, Some(wheel_resolution)) => {
+                info!("Resolution multiplier: {:#04x}", report);
+                wheel_resolution.set(report);
+            }
+            _ => warn!("Ignoring report {:?}: {=[u8]}", id, data),
+        }
         OutResponse::Accepted
     }
 
@@ -352,12 +576,14 @@ impl RequestHandler for MyRequestHandler {
 
 struct MyDeviceHandler {
     configured: AtomicBool,
+    wheel_resolution: &'static WheelResolution,
 }
 
 impl MyDeviceHandler {
-    fn new() -> Self {
+    fn new(wheel_resolution: &'static WheelResolution) -> Self {
         MyDeviceHandler {
             configured: AtomicBool::new(false),
+            wheel_resolution,
         }
     }
 }
@@ -370,6 +596,8 @@ impl embassy_usb::Handler for MyDeviceHandler {
 
     fn reset(&mut self) {
         self.configured.store(false, Ordering::Relaxed);
+        // The next host may not know about high resolution scrolling
+        self.wheel_resolution.set(0);
         info!("Bus reset, the Vbus current limit is 100mA");
     }
 
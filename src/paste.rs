@@ -0,0 +1,194 @@
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use defmt::*;
+use embassy_sync::{
+    blocking_mutex::{self, raw::NoopRawMutex},
+    mutex::Mutex,
+    signal::Signal,
+};
+use embassy_time::{Duration, Timer};
+
+use barrier_core::{
+    barrier::CLIPBOARD_BUFFER_SIZE,
+    synergy_hid::{KeyboardMode, Layout, ReportType, SynergyHid, UnicodeInput, MAX_REPORT_SIZE},
+};
+
+use crate::boot_hid::BootHidWriter;
+
+pub type KeyboardWriter<'d> =
+    BootHidWriter<'d, embassy_rp::usb::Driver<'d, embassy_rp::peripherals::USB>>;
+
+// Keyboard reports the actuator can hold back while a paste is typing
+const DEFERRED_REPORTS: usize = 16;
+
+struct PasteBuffer {
+    data: [u8; CLIPBOARD_BUFFER_SIZE],
+    len: usize,
+}
+
+struct Deferred {
+    reports: [([u8; MAX_REPORT_SIZE], usize); DEFERRED_REPORTS],
+    len: usize,
+}
+
+/**
+ * "Paste by typing" state shared between the actuator and the typing loop.
+ * The actuator queues the clipboard text when the hotkey is pressed, the loop types it out
+ * so the Barrier connection keeps being served while typing is in progress.
+ */
+pub struct Paste {
+    max_length: usize,
+    buffer: Mutex<NoopRawMutex, PasteBuffer>,
+    start: Signal<NoopRawMutex, ()>,
+    deferred: blocking_mutex::Mutex<NoopRawMutex, RefCell<Deferred>>,
+    active: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl Paste {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length: core::cmp::min(max_length, CLIPBOARD_BUFFER_SIZE),
+            buffer: Mutex::new(PasteBuffer {
+                data: [0; CLIPBOARD_BUFFER_SIZE],
+                len: 0,
+            }),
+            start: Signal::new(),
+            deferred: blocking_mutex::Mutex::new(RefCell::new(Deferred {
+                reports: [([0; MAX_REPORT_SIZE], 0); DEFERRED_REPORTS],
+                len: 0,
+            })),
+            active: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn request(&self, text: &str) -> bool {
+        if self.is_active() {
+            warn!("Paste already in progress");
+            return false;
+        }
+        let Ok(mut buffer) = self.buffer.try_lock() else {
+            return false;
+        };
+        let mut len = core::cmp::min(text.len(), self.max_length);
+        // Don't cut a character in half
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        if len < text.len() {
+            warn!("Clipboard text too long, typing only {} bytes", len);
+        }
+        buffer.data[..len].copy_from_slice(&text.as_bytes()[..len]);
+        buffer.len = len;
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.active.store(true, Ordering::Relaxed);
+        self.start.signal(());
+        true
+    }
+
+    pub fn cancel(&self) {
+        if self.is_active() {
+            info!("Paste cancelled");
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /**
+     * Holds back a keyboard report of the actuator while typing, the loop writes the held reports
+     * in order once the paste is done. False when no paste is active, the report can go out then.
+     * Past `DEFERRED_REPORTS` the last one is replaced, the host still ends up with the latest keys.
+     */
+    pub fn defer(&self, report: &[u8]) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+        self.deferred.lock(|deferred| {
+            let mut deferred = deferred.borrow_mut();
+            if deferred.len == DEFERRED_REPORTS {
+                warn!("Too many key events during paste, dropping one");
+                deferred.len -= 1;
+            }
+            let len = report.len().min(MAX_REPORT_SIZE);
+            let slot = deferred.len;
+            deferred.reports[slot].0[..len].copy_from_slice(&report[..len]);
+            deferred.reports[slot].1 = len;
+            deferred.len += 1;
+        });
+        debug!("Keyboard report held back until the paste is done");
+        true
+    }
+
+    fn next_deferred(&self) -> Option<([u8; MAX_REPORT_SIZE], usize)> {
+        self.deferred.lock(|deferred| {
+            let mut deferred = deferred.borrow_mut();
+            if deferred.len == 0 {
+                return None;
+            }
+            let report = deferred.reports[0];
+            deferred.reports.copy_within(1.., 0);
+            deferred.len -= 1;
+            Some(report)
+        })
+    }
+
+    pub async fn run(
+        &self,
+        keyboard_writer: &Mutex<NoopRawMutex, KeyboardWriter<'_>>,
+        keyboard_mode: KeyboardMode,
+        layout: Layout,
+        unicode_input: Option<UnicodeInput>,
+        key_delay: Duration,
+    ) -> ! {
+        let mut hid = SynergyHid::new(false);
+        hid.set_keyboard_mode(keyboard_mode);
+        hid.set_layout(layout);
+        hid.set_unicode_input(unicode_input);
+        let mut report = [0; MAX_REPORT_SIZE];
+        loop {
+            self.start.wait().await;
+            let buffer = self.buffer.lock().await;
+            info!("Typing {} bytes", buffer.len);
+            hid.set_keyboard_protocol(keyboard_writer.lock().await.protocol().get());
+
+            // Release whatever the hotkey left pressed on the host
+            let (_, r) = hid.clear(ReportType::Keyboard, &mut report);
+            keyboard_writer.lock().await.write(r).await.ok();
+            Timer::after(key_delay).await;
+
+            let text = core::str::from_utf8(&buffer.data[..buffer.len]).unwrap_or("");
+            for c in text.chars() {
+                if self.cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Some((_, r)) = hid.char_down(c, &mut report) {
+                    keyboard_writer.lock().await.write(r).await.ok();
+                    Timer::after(key_delay).await;
+                }
+                while let Some((_, r)) = hid.next_report(&mut report) {
+                    keyboard_writer.lock().await.write(r).await.ok();
+                    Timer::after(key_delay).await;
+                }
+                if let Some((_, r)) = hid.char_up(c, &mut report) {
+                    keyboard_writer.lock().await.write(r).await.ok();
+                    Timer::after(key_delay).await;
+                }
+            }
+
+            let (_, r) = hid.clear(ReportType::Keyboard, &mut report);
+            keyboard_writer.lock().await.write(r).await.ok();
+            // Then the key events of the server that came in meanwhile, the last one holds the
+            // keys that are still down. Nothing can be deferred between the last check and
+            // `active` going false, there's no await in between.
+            while let Some((r, len)) = self.next_deferred() {
+                Timer::after(key_delay).await;
+                keyboard_writer.lock().await.write(&r[..len]).await.ok();
+            }
+            self.active.store(false, Ordering::Relaxed);
+        }
+    }
+}
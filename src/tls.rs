@@ -0,0 +1,119 @@
+use defmt::*;
+use embassy_rp::clocks::RoscRng;
+use embedded_io_async::{Read, Write};
+use embedded_tls::{
+    Aes128GcmSha256, Certificate, CertificateEntryRef, CertificateRef, HandshakeVerifyRef,
+    SignatureScheme, TlsCipherSuite, TlsConfig, TlsConnection, TlsContext, TlsError, TlsVerifier,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::Digest;
+
+use barrier_core::barrier::ConnectionError;
+use barrier_core::tls::{fingerprint, parse_fingerprint, CertificateError, PinnedKey, PssHash};
+
+pub type TlsStream<'a, S> = TlsConnection<'a, S, Aes128GcmSha256>;
+
+// TLS 1.3 allows records up to 16KB plus overhead, the server decides how large they are
+pub const TLS_READ_BUFFER_SIZE: usize = 16640;
+pub const TLS_WRITE_BUFFER_SIZE: usize = 4096;
+
+pub async fn connect<'a, S: Read + Write>(
+    socket: S,
+    read_buffer: &'a mut [u8],
+    write_buffer: &'a mut [u8],
+) -> Result<TlsStream<'a, S>, ConnectionError> {
+    let config = TlsConfig::new();
+    let mut tls = TlsConnection::new(socket, read_buffer, write_buffer);
+    let mut rng = Rng(RoscRng);
+    tls.open::<_, PinnedVerifier<Aes128GcmSha256>>(TlsContext::new(&config, &mut rng))
+        .await
+        .map_err(|e| {
+            warn!("TLS handshake failed: {:?}", e);
+            ConnectionError::TlsError
+        })?;
+    Ok(tls)
+}
+
+// The ring oscillator is the only entropy source on the RP2040
+struct Rng(RoscRng);
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for Rng {}
+
+/**
+ * Accepts the server only if its certificate matches `SERVER_FINGERPRINT`, and checks the
+ * CertificateVerify signature so the certificate can't be replayed without its private key.
+ */
+struct PinnedVerifier<CipherSuite: TlsCipherSuite> {
+    key: Option<PinnedKey>,
+    transcript: Option<CipherSuite::Hash>,
+}
+
+impl<'a, CipherSuite: TlsCipherSuite> TlsVerifier<'a, CipherSuite> for PinnedVerifier<CipherSuite> {
+    fn new(_host: Option<&'a str>) -> Self {
+        Self {
+            key: None,
+            transcript: None,
+        }
+    }
+
+    fn verify_certificate(
+        &mut self,
+        transcript: &CipherSuite::Hash,
+        _ca: &Option<Certificate>,
+        cert: CertificateRef,
+    ) -> Result<(), TlsError> {
+        let Some(CertificateEntryRef::X509(der)) = cert.entries.first() else {
+            return Err(TlsError::InvalidCertificate);
+        };
+        let pinned = crate::SERVER_FINGERPRINT
+            .and_then(parse_fingerprint)
+            .ok_or(TlsError::InvalidCertificate)?;
+        let key = PinnedKey::from_certificate(der, &pinned).map_err(|e| {
+            match e {
+                CertificateError::FingerprintMismatch => {
+                    warn!("Server fingerprint mismatch: {=[u8]:02X}", fingerprint(der))
+                }
+                _ => warn!("Server certificate rejected: {}", e),
+            }
+            TlsError::InvalidCertificate
+        })?;
+        self.key = Some(key);
+        self.transcript = Some(transcript.clone());
+        Ok(())
+    }
+
+    fn verify_signature(&mut self, verify: HandshakeVerifyRef) -> Result<(), TlsError> {
+        let (Some(key), Some(transcript)) = (self.key.take(), self.transcript.take()) else {
+            return Err(TlsError::InvalidSignature);
+        };
+        let hash = match verify.signature_scheme {
+            SignatureScheme::RsaPssRsaeSha256 => PssHash::Sha256,
+            SignatureScheme::RsaPssRsaeSha384 => PssHash::Sha384,
+            SignatureScheme::RsaPssRsaeSha512 => PssHash::Sha512,
+            _ => return Err(TlsError::InvalidSignatureScheme),
+        };
+        key.verify(hash, &transcript.finalize(), verify.signature)
+            .map_err(|_| {
+                warn!("Server CertificateVerify signature is invalid");
+                TlsError::InvalidSignature
+            })
+    }
+}
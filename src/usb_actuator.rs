@@ -1,37 +1,53 @@
 use defmt::info;
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Sender};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Sender, mutex::Mutex};
 use embassy_usb::class::hid::HidWriter;
 
+use barrier_core::{
+    barrier::{Options, CLIPBOARD_BUFFER_SIZE},
+    hid_actuator::ReportSink,
+    synergy_hid::{KeyboardLeds, Protocol, ReportType},
+};
+
 use crate::{
-    barrier::Actuator,
+    boot_hid::BootHidWriter,
+    host_state::{HostLeds, HostProtocol, WheelResolution},
     indicator::IndicatorStatus,
-    synergy_hid::{ReportType, SynergyHid},
+    paste::{KeyboardWriter, Paste},
 };
 
+// Shift, Control, Alt, Meta, Super and AltGr, lock states are ignored when matching the hotkey
+const HOTKEY_MODIFIER_MASK: u16 = 0x003F;
+
+/**
+ * Sends the reports of the `HidActuator` to the USB host, and takes the paste hotkey for the
+ * paste loop.
+ */
 pub struct UsbActuator<'a, 'b, 'c, 'd> {
-    width: u16,
-    height: u16,
-    x: u16,
-    y: u16,
-    hid: SynergyHid,
+    clipboard: [u8; CLIPBOARD_BUFFER_SIZE],
+    clipboard_len: usize,
+    paste: &'a Paste,
+    paste_hotkey: u16,
+    paste_hotkey_mask: u16,
     sender: Sender<'a, NoopRawMutex, IndicatorStatus, 4>,
-    keyboard_writer: HidWriter<'b, embassy_rp::usb::Driver<'b, embassy_rp::peripherals::USB>, 8>,
-    mouse_writer: HidWriter<'c, embassy_rp::usb::Driver<'c, embassy_rp::peripherals::USB>, 7>,
+    keyboard_leds: &'a HostLeds,
+    wheel_resolution: &'a WheelResolution,
+    keyboard_writer: &'b Mutex<NoopRawMutex, KeyboardWriter<'b>>,
+    keyboard_protocol: &'b HostProtocol,
+    mouse_writer: BootHidWriter<'c, embassy_rp::usb::Driver<'c, embassy_rp::peripherals::USB>>,
     consumer_writer: HidWriter<'d, embassy_rp::usb::Driver<'d, embassy_rp::peripherals::USB>, 2>,
 }
 
 impl<'a, 'b, 'c, 'd> UsbActuator<'a, 'b, 'c, 'd> {
     pub fn new(
-        width: u16,
-        height: u16,
-        flip_mouse_wheel: bool,
+        paste: &'a Paste,
+        paste_hotkey: u16,
+        paste_hotkey_mask: u16,
         sender: Sender<'a, NoopRawMutex, IndicatorStatus, 4>,
-        keyboard_writer: HidWriter<
-            'b,
-            embassy_rp::usb::Driver<'b, embassy_rp::peripherals::USB>,
-            8,
-        >,
-        mouse_writer: HidWriter<'c, embassy_rp::usb::Driver<'c, embassy_rp::peripherals::USB>, 7>,
+        keyboard_leds: &'a HostLeds,
+        wheel_resolution: &'a WheelResolution,
+        keyboard_writer: &'b Mutex<NoopRawMutex, KeyboardWriter<'b>>,
+        keyboard_protocol: &'b HostProtocol,
+        mouse_writer: BootHidWriter<'c, embassy_rp::usb::Driver<'c, embassy_rp::peripherals::USB>>,
         consumer_writer: HidWriter<
             'd,
             embassy_rp::usb::Driver<'d, embassy_rp::peripherals::USB>,
@@ -39,23 +55,32 @@ impl<'a, 'b, 'c, 'd> UsbActuator<'a, 'b, 'c, 'd> {
         >,
     ) -> Self {
         Self {
-            width,
-            height,
-            x: 0,
-            y: 0,
-            hid: SynergyHid::new(flip_mouse_wheel),
+            clipboard: [0; CLIPBOARD_BUFFER_SIZE],
+            clipboard_len: 0,
+            paste,
+            paste_hotkey,
+            paste_hotkey_mask,
             sender,
+            keyboard_leds,
+            wheel_resolution,
             keyboard_writer,
+            keyboard_protocol,
             mouse_writer,
             consumer_writer,
         }
     }
+}
 
-    pub async fn send_report(&mut self, report: (ReportType, &[u8])) {
+impl<'a, 'b, 'c, 'd> ReportSink for UsbActuator<'a, 'b, 'c, 'd> {
+    async fn send_report(&mut self, report: (ReportType, &[u8])) {
         info!("Sending report: {}, {}", report.0 as u8, report.1);
         match report.0 {
             ReportType::Keyboard => {
-                self.keyboard_writer.write(report.1).await.ok();
+                // The paste loop owns the keyboard while it's typing, it sends the report afterwards
+                if self.paste.defer(report.1) {
+                    return;
+                }
+                self.keyboard_writer.lock().await.write(report.1).await.ok();
             }
             ReportType::Mouse => {
                 self.mouse_writer.write(report.1).await.ok();
@@ -66,66 +91,43 @@ impl<'a, 'b, 'c, 'd> UsbActuator<'a, 'b, 'c, 'd> {
         }
     }
 
-    pub(crate) fn scale_position(&self, x: u16, y: u16) -> (u16, u16) {
-        // Scale screen position to HID position
-        (
-            ((x as f32) * (0x7fff as f32) / (self.width as f32)) as u16,
-            ((y as f32) * (0x7fff as f32) / (self.height as f32)) as u16,
-        )
+    fn keyboard_protocol(&self) -> Protocol {
+        self.keyboard_protocol.get()
     }
-}
 
-impl<'a, 'b, 'c, 'd> Actuator for UsbActuator<'a, 'b, 'c, 'd> {
-    async fn connected(&mut self) {
-        info!("Connected to Barrier");
-        self.sender.send(IndicatorStatus::ServerConnected).await;
-    }
-
-    async fn disconnected(&mut self) {
-        info!("Disconnected from Barrier");
-        self.sender.send(IndicatorStatus::ServerDisconnected).await;
+    fn mouse_protocol(&self) -> Protocol {
+        self.mouse_writer.protocol().get()
     }
 
-    async fn get_screen_size(&self) -> (u16, u16) {
-        // TODO:
-        (self.width, self.height)
+    fn resolution_multiplier(&self) -> u8 {
+        self.wheel_resolution.get()
     }
 
-    async fn get_cursor_position(&self) -> (u16, u16) {
-        (self.x, self.y)
+    fn keyboard_leds(&self) -> KeyboardLeds {
+        self.keyboard_leds.get()
     }
 
-    async fn set_cursor_position(&mut self, x: u16, y: u16) {
-        self.x = x;
-        self.y = y;
-        let (phy_x, phy_y) = self.scale_position(x, y);
-        let mut report = [0; 9];
-        let ret = self.hid.set_cursor_position(phy_x, phy_y, &mut report);
-        self.send_report(ret).await;
-    }
-
-    async fn mouse_down(&mut self, button: i8) {
-        let mut report = [0; 9];
-        let ret = self.hid.mouse_down(button, &mut report);
-        self.send_report(ret).await;
-    }
-
-    async fn mouse_up(&mut self, button: i8) {
-        let mut report = [0; 9];
-        let ret = self.hid.mouse_up(button, &mut report);
-        self.send_report(ret).await;
+    async fn take_key(&mut self, key: u16, mask: u16, _button: u16) -> bool {
+        if self.paste_hotkey == 0
+            || key != self.paste_hotkey
+            || mask & HOTKEY_MODIFIER_MASK != self.paste_hotkey_mask
+        {
+            return false;
+        }
+        let text = core::str::from_utf8(&self.clipboard[..self.clipboard_len]).unwrap_or("");
+        self.paste.request(text);
+        true
     }
 
-    async fn mouse_wheel(&mut self, x: i16, y: i16) {
-        let mut report = [0; 9];
-        let ret = self.hid.mouse_scroll(x, y, &mut report);
-        self.send_report(ret).await;
+    async fn connected(&mut self) {
+        info!("Connected to Barrier");
+        self.sender.send(IndicatorStatus::ServerConnected).await;
     }
 
-    async fn key_down(&mut self, key: u16, mask: u16, button: u16) {
-        let mut report = [0; 9];
-        let ret = self.hid.key_down(key, mask, button, &mut report);
-        self.send_report(ret).await;
+    async fn disconnected(&mut self) {
+        info!("Disconnected from Barrier");
+        self.paste.cancel();
+        self.sender.send(IndicatorStatus::ServerDisconnected).await;
     }
 
     async fn key_repeat(&mut self, key: u16, mask: u16, button: u16, count: u16) {
@@ -135,30 +137,35 @@ impl<'a, 'b, 'c, 'd> Actuator for UsbActuator<'a, 'b, 'c, 'd> {
         )
     }
 
-    async fn key_up(&mut self, key: u16, mask: u16, button: u16) {
-        let mut report = [0; 9];
-        let ret = self.hid.key_up(key, mask, button, &mut report);
-        self.send_report(ret).await;
+    async fn set_clipboard(&mut self, id: u8, text: &str) {
+        info!("Got clipboard {}, {} bytes", id, text.len());
+        let len = core::cmp::min(text.len(), self.clipboard.len());
+        self.clipboard[..len].copy_from_slice(&text.as_bytes()[..len]);
+        self.clipboard_len = len;
+    }
+
+    async fn set_options(&mut self, options: Options) {
+        info!(
+            "Setting options, heartbeat: {}ms, half duplex caps: {}, num: {}, scroll: {}",
+            options.heartbeat_ms,
+            options.half_duplex_caps_lock,
+            options.half_duplex_num_lock,
+            options.half_duplex_scroll_lock
+        );
     }
 
     async fn reset_options(&mut self) {
-        info!("Resetting options")
+        info!("Resetting options");
     }
 
-    async fn enter(&mut self) {
-        info!("Entering");
+    async fn enter(&mut self, x: u16, y: u16, mask: u16) {
+        info!("Entering at {}, {}, mask: {}", x, y, mask);
         self.sender.send(IndicatorStatus::EnterScreen).await;
     }
 
     async fn leave(&mut self) {
         info!("Leaving");
-        let mut report = [0; 9];
-        let ret = self.hid.clear(ReportType::Keyboard, &mut report);
-        self.send_report(ret).await;
-        let ret = self.hid.clear(ReportType::Mouse, &mut report);
-        self.send_report(ret).await;
-        let ret = self.hid.clear(ReportType::Consumer, &mut report);
-        self.send_report(ret).await;
+        self.paste.cancel();
         self.sender.send(IndicatorStatus::LeaveScreen).await;
     }
 }